@@ -0,0 +1,354 @@
+//! Running transactions off the `tokio` runtime threads.
+
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Arc;
+use std::thread;
+
+use tokio::sync::{mpsc, oneshot, Semaphore};
+
+use crate::constants::MDBX_EINTR;
+use crate::environment::Environment;
+use crate::error::{io_error, Error, Result};
+use crate::transaction::{RoTxn, RwTxn};
+
+/// Default number of writes queued before [`AsyncEnvironment::write`] waits.
+const DEFAULT_WRITE_QUEUE: usize = 64;
+/// Default number of read closures running at once.
+const DEFAULT_MAX_READS: usize = 32;
+
+type WriteJob = Box<dyn FnOnce(&Environment) + Send>;
+type Outcome<R> = thread::Result<Result<R>>;
+
+/// Async front end for an [`Environment`].
+///
+/// Writes run one at a time on a dedicated writer thread and reads on
+/// `tokio`'s blocking pool, so no engine call blocks the runtime. Both queues
+/// are bounded: `write` and `read` wait for room before submitting.
+///
+/// Dropping a returned future skips a closure that hasn't started yet, and a
+/// write whose future is gone by the time the closure returns is aborted.
+/// Cancellation is best effort: a future dropped while the write is
+/// committing doesn't undo the commit, so a cancelled write may still have
+/// been applied. Closures are run through [`Environment::read`] and
+/// [`Environment::write`], so they may be replayed.
+///
+/// ```rust,ignore
+/// let env = AsyncEnvironment::builder().max_reads(16).build(Arc::new(env))?;
+/// env.write(move |txn| txn.put(db, b"key", b"value", PutFlags::empty())).await?;
+/// let value = env.read(move |txn| Ok(txn.get(db, b"key")?.map(Value::into_owned))).await?;
+/// ```
+#[derive(Debug, Clone)]
+pub struct AsyncEnvironment {
+    env: Arc<Environment>,
+    writes: mpsc::Sender<WriteJob>,
+    reads: Arc<Semaphore>,
+}
+
+impl AsyncEnvironment {
+    /// Configure an async front end.
+    pub fn builder() -> AsyncEnvironmentBuilder {
+        AsyncEnvironmentBuilder::default()
+    }
+
+    /// Async front end with the default queue sizes.
+    pub fn new(env: Arc<Environment>) -> Result<Self> {
+        AsyncEnvironment::builder().build(env)
+    }
+
+    /// The underlying environment.
+    #[inline]
+    pub fn env(&self) -> &Arc<Environment> {
+        &self.env
+    }
+
+    /// Run `f` in a write transaction on the writer thread and commit it.
+    ///
+    /// If the future is dropped after `f` returns, the write may commit
+    /// anyway; see the [type docs](AsyncEnvironment).
+    pub async fn write<F, R>(&self, mut f: F) -> Result<R>
+    where
+        F: FnMut(&RwTxn<'_>) -> Result<R> + Send + 'static,
+        R: Send + 'static,
+    {
+        let (sender, receiver) = oneshot::channel::<Outcome<R>>();
+        let job: WriteJob = Box::new(move |env| {
+            if sender.is_closed() {
+                return;
+            }
+            let outcome = panic::catch_unwind(AssertUnwindSafe(|| {
+                env.write(|txn| {
+                    let value = f(txn)?;
+                    if sender.is_closed() {
+                        // Nobody is waiting, so abort instead of committing;
+                        // the future may still be dropped after this check
+                        return Err(Error::from_code(MDBX_EINTR));
+                    }
+                    Ok(value)
+                })
+            }));
+            let _ = sender.send(outcome);
+        });
+        self.writes.send(job).await.map_err(|_| stopped())?;
+        receive(receiver).await
+    }
+
+    /// Run `f` in a read-only transaction on the blocking pool.
+    pub async fn read<F, R>(&self, mut f: F) -> Result<R>
+    where
+        F: FnMut(&RoTxn<'_>) -> Result<R> + Send + 'static,
+        R: Send + 'static,
+    {
+        let permit = Arc::clone(&self.reads)
+            .acquire_owned()
+            .await
+            .map_err(|_| stopped())?;
+        let env = Arc::clone(&self.env);
+        let (sender, receiver) = oneshot::channel::<Outcome<R>>();
+        tokio::task::spawn_blocking(move || {
+            let _permit = permit;
+            if sender.is_closed() {
+                return;
+            }
+            let outcome = panic::catch_unwind(AssertUnwindSafe(|| env.read(&mut f)));
+            let _ = sender.send(outcome);
+        });
+        receive(receiver).await
+    }
+}
+
+/// Wait for a closure's outcome, resuming its panic if it had one.
+async fn receive<R>(receiver: oneshot::Receiver<Outcome<R>>) -> Result<R> {
+    match receiver.await {
+        Ok(Ok(result)) => result,
+        Ok(Err(payload)) => panic::resume_unwind(payload),
+        Err(_) => Err(stopped()),
+    }
+}
+
+/// The writer thread or the read pool is gone.
+fn stopped() -> Error {
+    Error::from_code(MDBX_EINTR)
+}
+
+/// Queue sizes for an [`AsyncEnvironment`].
+#[derive(Debug, Clone)]
+pub struct AsyncEnvironmentBuilder {
+    write_queue: usize,
+    max_reads: usize,
+}
+
+impl Default for AsyncEnvironmentBuilder {
+    fn default() -> Self {
+        AsyncEnvironmentBuilder {
+            write_queue: DEFAULT_WRITE_QUEUE,
+            max_reads: DEFAULT_MAX_READS,
+        }
+    }
+}
+
+impl AsyncEnvironmentBuilder {
+    /// Writes queued for the writer thread before `write` waits.
+    pub fn write_queue(mut self, len: usize) -> Self {
+        self.write_queue = len;
+        self
+    }
+
+    /// Read closures running at once before `read` waits; capped at the
+    /// environment's `maxreaders`.
+    pub fn max_reads(mut self, count: usize) -> Self {
+        self.max_reads = count;
+        self
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.write_queue == 0 {
+            return Err(Error::InvalidOption("write queue must not be empty"));
+        }
+        if self.max_reads == 0 {
+            return Err(Error::InvalidOption("max_reads must be at least 1"));
+        }
+        Ok(())
+    }
+
+    /// Start the writer thread for `env`.
+    ///
+    /// The thread finishes the queued writes and exits once every clone of
+    /// the returned handle is dropped.
+    pub fn build(&self, env: Arc<Environment>) -> Result<AsyncEnvironment> {
+        self.validate()?;
+        let max_reads = self.max_reads.min(env.max_readers()? as usize);
+        let (writes, mut jobs) = mpsc::channel::<WriteJob>(self.write_queue);
+        let writer_env = Arc::clone(&env);
+        thread::Builder::new()
+            .name("mdbx-writer".to_owned())
+            .spawn(move || {
+                while let Some(job) = jobs.blocking_recv() {
+                    job(&writer_env);
+                }
+            })
+            .map_err(io_error)?;
+        Ok(AsyncEnvironment {
+            env,
+            writes,
+            reads: Arc::new(Semaphore::new(max_reads)),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::task::Poll;
+    use std::time::Duration;
+
+    use super::*;
+    use crate::flags::{DbFlags, PutFlags};
+    use crate::test_util::TestEnv;
+    use crate::transaction::Txn;
+
+    #[test]
+    fn test_validate() {
+        assert!(AsyncEnvironmentBuilder::default().validate().is_ok());
+        assert!(AsyncEnvironment::builder()
+            .write_queue(0)
+            .validate()
+            .is_err());
+        assert!(AsyncEnvironment::builder().max_reads(0).validate().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_write_then_read() {
+        let env = TestEnv::new();
+        let async_env = AsyncEnvironment::new(Arc::clone(&env.env)).unwrap();
+        let db = async_env
+            .write(|txn| {
+                let db = txn.create_db(None, DbFlags::empty())?;
+                txn.put(db, b"key", b"value", PutFlags::empty())?;
+                Ok(db)
+            })
+            .await
+            .unwrap();
+        let value = async_env
+            .read(move |txn| Ok(txn.get(db, b"key")?.map(|value| value.to_vec())))
+            .await
+            .unwrap();
+        assert_eq!(value.as_deref(), Some(&b"value"[..]));
+
+        let err = async_env
+            .write(|_| Err::<(), _>(Error::InvalidOption("rejected")))
+            .await
+            .unwrap_err();
+        assert_eq!(err, Error::InvalidOption("rejected"));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_read_bound() {
+        let env = TestEnv::new();
+        let async_env = AsyncEnvironment::builder()
+            .max_reads(2)
+            .build(Arc::clone(&env.env))
+            .unwrap();
+        let running = Arc::new(AtomicUsize::new(0));
+        let most = Arc::new(AtomicUsize::new(0));
+        let reads: Vec<_> = (0..6)
+            .map(|_| {
+                let (running, most) = (Arc::clone(&running), Arc::clone(&most));
+                async_env.read(move |_| {
+                    let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                    most.fetch_max(now, Ordering::SeqCst);
+                    std::thread::sleep(Duration::from_millis(20));
+                    running.fetch_sub(1, Ordering::SeqCst);
+                    Ok(())
+                })
+            })
+            .collect();
+        for read in reads {
+            read.await.unwrap();
+        }
+        assert!(most.load(Ordering::SeqCst) <= 2);
+    }
+
+    #[tokio::test]
+    async fn test_panic_is_resumed() {
+        let env = TestEnv::new();
+        let async_env = AsyncEnvironment::new(Arc::clone(&env.env)).unwrap();
+        let panicking = async_env.clone();
+        let err =
+            tokio::spawn(
+                async move { panicking.write(|_| -> Result<()> { panic!("boom") }).await },
+            )
+            .await
+            .unwrap_err();
+        let payload = err.into_panic();
+        assert_eq!(payload.downcast_ref::<&str>(), Some(&"boom"));
+
+        // The writer thread survives the panic
+        async_env.write(|_| Ok(())).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_write_is_skipped() {
+        let env = TestEnv::new();
+        let async_env = AsyncEnvironment::new(Arc::clone(&env.env)).unwrap();
+        let (started, wait_started) = std::sync::mpsc::channel();
+        let (release, released) = std::sync::mpsc::channel::<()>();
+        let blocker = tokio::spawn({
+            let async_env = async_env.clone();
+            async move {
+                async_env
+                    .write(move |_| {
+                        let _ = started.send(());
+                        let _ = released.recv();
+                        Ok(())
+                    })
+                    .await
+            }
+        });
+        tokio::task::spawn_blocking(move || wait_started.recv().unwrap())
+            .await
+            .unwrap();
+
+        // Queue a write behind the blocked one, then drop its future
+        let ran = Arc::new(AtomicBool::new(false));
+        let flag = Arc::clone(&ran);
+        let mut cancelled = Box::pin(async_env.write(move |_| {
+            flag.store(true, Ordering::SeqCst);
+            Ok(())
+        }));
+        assert!(poll_once(cancelled.as_mut()).await.is_none());
+        drop(cancelled);
+
+        release.send(()).unwrap();
+        blocker.await.unwrap().unwrap();
+        async_env.write(|_| Ok(())).await.unwrap();
+        assert!(!ran.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn test_writer_stops_with_last_handle() {
+        let env = TestEnv::new();
+        let async_env = AsyncEnvironment::new(Arc::clone(&env.env)).unwrap();
+        async_env.write(|_| Ok(())).await.unwrap();
+        assert_eq!(Arc::strong_count(&env.env), 3);
+        drop(async_env);
+        // The writer thread drops its handle once the queue is closed
+        for _ in 0..100 {
+            if Arc::strong_count(&env.env) == 1 {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("writer thread kept the environment open");
+    }
+
+    /// Poll `future` once, returning its output if it is ready.
+    async fn poll_once<F: Future + Unpin>(mut future: F) -> Option<F::Output> {
+        std::future::poll_fn(|cx| match Pin::new(&mut future).poll(cx) {
+            Poll::Ready(output) => Poll::Ready(Some(output)),
+            Poll::Pending => Poll::Ready(None),
+        })
+        .await
+    }
+}
@@ -0,0 +1,661 @@
+//! Bulk loading with `MDBX_APPEND`.
+
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{self, AtomicU64};
+
+use crate::constants::MDBX_BAD_VALSIZE;
+use crate::database::Database;
+use crate::environment::Environment;
+use crate::error::{io_error, Error, Result};
+use crate::flags::{DbFlags, PutFlags};
+use crate::ordering::KeyOrdering;
+use crate::transaction::Txn;
+
+/// Default dirty bytes per transaction before committing.
+const DEFAULT_COMMIT_BYTES: u64 = 256 << 20;
+/// Default memory used to sort unsorted input before spilling a run.
+const DEFAULT_SORT_BUFFER: usize = 256 << 20;
+/// Entries written between two `mdbx_txn_info` checks.
+const CHECK_INTERVAL: u32 = 1024;
+/// Bookkeeping bytes counted for each buffered entry.
+const ENTRY_OVERHEAD: usize = 2 * std::mem::size_of::<Vec<u8>>();
+
+type Pair = (Vec<u8>, Vec<u8>);
+
+/// Loads key/value pairs with `MDBX_APPEND`, committing in chunks.
+///
+/// ```rust,ignore
+/// let stats = BulkLoader::new(&env, db)
+///     .commit_every(64 << 20)
+///     .load(rows)?;
+/// ```
+///
+/// `db` must exist in a committed transaction. Each chunk is committed on
+/// its own, so an error leaves the chunks before it in place.
+#[derive(Debug)]
+pub struct BulkLoader<'env> {
+    env: &'env Environment,
+    db: Database,
+    commit_every: u64,
+    sort_buffer: usize,
+    temp_dir: Option<PathBuf>,
+}
+
+/// Counters returned by a bulk load.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct BulkStats {
+    /// Entries written.
+    pub entries: u64,
+    /// Key and value bytes written.
+    pub bytes: u64,
+    /// Transactions committed.
+    pub commits: u64,
+    /// Sorted runs spilled to temporary files.
+    pub runs: u64,
+}
+
+impl<'env> BulkLoader<'env> {
+    /// Create a loader for `db`.
+    pub fn new(env: &'env Environment, db: Database) -> Self {
+        BulkLoader {
+            env,
+            db,
+            commit_every: DEFAULT_COMMIT_BYTES,
+            sort_buffer: DEFAULT_SORT_BUFFER,
+            temp_dir: None,
+        }
+    }
+
+    /// Commit once a transaction has dirtied this many bytes.
+    pub fn commit_every(mut self, bytes: u64) -> Self {
+        self.commit_every = bytes;
+        self
+    }
+
+    /// Memory used by [`load_unsorted`](Self::load_unsorted) per sorted run.
+    pub fn sort_buffer(mut self, bytes: usize) -> Self {
+        self.sort_buffer = bytes;
+        self
+    }
+
+    /// Directory for the runs of [`load_unsorted`](Self::load_unsorted),
+    /// the system temporary directory by default.
+    pub fn temp_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.temp_dir = Some(dir.into());
+        self
+    }
+
+    /// Load pairs sorted in database order.
+    ///
+    /// Keys (and values of `MDBX_DUPSORT` tables) must be strictly increasing
+    /// and sort after the entries already stored; otherwise the load stops
+    /// with [`Error::OutOfOrder`] naming the first offending entry.
+    pub fn load<I, K, V>(&self, pairs: I) -> Result<BulkStats>
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: AsRef<[u8]>,
+        V: AsRef<[u8]>,
+    {
+        self.load_sorted(pairs.into_iter().map(Ok))
+    }
+
+    /// Load pairs in any order by sorting them through temporary files.
+    ///
+    /// When a key occurs more than once the last value wins; repeated pairs
+    /// of `MDBX_DUPSORT` tables are stored once.
+    pub fn load_unsorted<I, K, V>(&self, pairs: I) -> Result<BulkStats>
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: AsRef<[u8]>,
+        V: AsRef<[u8]>,
+    {
+        let order = self.order()?;
+        let temp_dir = self.temp_dir.clone().unwrap_or_else(std::env::temp_dir);
+        let mut runs = Vec::new();
+        let mut buffer = Vec::new();
+        let mut buffered = 0;
+        for (key, data) in pairs {
+            let (key, data) = (key.as_ref().to_vec(), data.as_ref().to_vec());
+            buffered += key.len() + data.len() + ENTRY_OVERHEAD;
+            buffer.push((key, data));
+            if buffered >= self.sort_buffer {
+                order.sort(&mut buffer);
+                runs.push(RunFile::write(&temp_dir, &buffer)?);
+                buffer.clear();
+                buffered = 0;
+            }
+        }
+        order.sort(&mut buffer);
+        if runs.is_empty() {
+            return self.load_sorted(Dedup::new(buffer.into_iter().map(Ok), order));
+        }
+        if !buffer.is_empty() {
+            runs.push(RunFile::write(&temp_dir, &buffer)?);
+        }
+        drop(buffer);
+        let run_count = runs.len() as u64;
+        let mut stats = self.load_sorted(Dedup::new(Merge::new(runs, order)?, order))?;
+        stats.runs = run_count;
+        Ok(stats)
+    }
+
+    fn load_sorted<K, V>(&self, pairs: impl Iterator<Item = Result<(K, V)>>) -> Result<BulkStats>
+    where
+        K: AsRef<[u8]>,
+        V: AsRef<[u8]>,
+    {
+        let order = self.order()?;
+        let flags = if self.db.flags().contains(DbFlags::DUPSORT) {
+            PutFlags::APPEND | PutFlags::APPENDDUP
+        } else {
+            PutFlags::APPEND
+        };
+        let mut pairs = pairs.peekable();
+        let mut stats = BulkStats::default();
+        let mut prev: Option<Pair> = None;
+        while pairs.peek().is_some() {
+            let txn = self.env.begin_rw_txn()?;
+            let mut cursor = txn.cursor(self.db)?;
+            if prev.is_none() {
+                prev = cursor
+                    .last()?
+                    .map(|(key, data)| (key.into_owned(), data.into_owned()));
+            }
+            let mut since_check = 0;
+            for pair in pairs.by_ref() {
+                let (key, data) = pair?;
+                let (key, data) = (key.as_ref(), data.as_ref());
+                check_order(order, prev.as_ref(), (key, data), stats.entries)?;
+                cursor.put(key, data, flags)?;
+                let prev = prev.get_or_insert_with(Default::default);
+                prev.0.clear();
+                prev.0.extend_from_slice(key);
+                prev.1.clear();
+                prev.1.extend_from_slice(data);
+                stats.entries += 1;
+                stats.bytes += (key.len() + data.len()) as u64;
+                since_check += 1;
+                if since_check == CHECK_INTERVAL {
+                    since_check = 0;
+                    if txn.info()?.space_dirty >= self.commit_every {
+                        break;
+                    }
+                }
+            }
+            drop(cursor);
+            txn.commit()?;
+            stats.commits += 1;
+        }
+        Ok(stats)
+    }
+
+    fn order(&self) -> Result<PairOrder<impl ByteCmp>> {
+        let flags = self.db.flags();
+        let data = if flags.contains(DbFlags::DUPSORT) {
+            Some(KeyOrdering::for_dup_flags(flags)?.order_fn())
+        } else {
+            None
+        };
+        Ok(PairOrder {
+            key: KeyOrdering::for_flags(flags)?.order_fn(),
+            data,
+        })
+    }
+}
+
+/// An infallible byte-string comparator such as `KeyOrdering::order_fn`.
+trait ByteCmp: Fn(&[u8], &[u8]) -> Ordering + Copy {}
+
+impl<F: Fn(&[u8], &[u8]) -> Ordering + Copy> ByteCmp for F {}
+
+/// Database order of key/value pairs; values only count for `MDBX_DUPSORT`.
+#[derive(Clone, Copy)]
+struct PairOrder<F> {
+    key: F,
+    data: Option<F>,
+}
+
+impl<F: ByteCmp> PairOrder<F> {
+    fn compare(&self, a: (&[u8], &[u8]), b: (&[u8], &[u8])) -> Ordering {
+        (self.key)(a.0, b.0).then_with(|| match self.data {
+            Some(data) => data(a.1, b.1),
+            None => Ordering::Equal,
+        })
+    }
+
+    /// Stable sort, so later duplicates stay after earlier ones.
+    fn sort(&self, pairs: &mut [Pair]) {
+        pairs.sort_by(|a, b| self.compare((&a.0, &a.1), (&b.0, &b.1)));
+    }
+}
+
+fn check_order<F: ByteCmp>(
+    order: PairOrder<F>,
+    prev: Option<&Pair>,
+    pair: (&[u8], &[u8]),
+    index: u64,
+) -> Result<()> {
+    match prev {
+        Some(prev) if order.compare((&prev.0, &prev.1), pair) != Ordering::Less => {
+            Err(Error::OutOfOrder {
+                index,
+                key: pair.0.to_vec(),
+            })
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Collapses equal neighbours of a sorted stream, keeping the last.
+struct Dedup<I, F> {
+    inner: I,
+    order: PairOrder<F>,
+    pending: Option<Pair>,
+}
+
+impl<I, F> Dedup<I, F> {
+    fn new(inner: I, order: PairOrder<F>) -> Self {
+        Dedup {
+            inner,
+            order,
+            pending: None,
+        }
+    }
+}
+
+impl<I, F> Iterator for Dedup<I, F>
+where
+    I: Iterator<Item = Result<Pair>>,
+    F: ByteCmp,
+{
+    type Item = Result<Pair>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let next = match self.inner.next() {
+                Some(Ok(next)) => next,
+                Some(Err(err)) => return Some(Err(err)),
+                None => return self.pending.take().map(Ok),
+            };
+            match self.pending.take() {
+                Some(pending)
+                    if self
+                        .order
+                        .compare((&pending.0, &pending.1), (&next.0, &next.1))
+                        != Ordering::Equal =>
+                {
+                    self.pending = Some(next);
+                    return Some(Ok(pending));
+                }
+                _ => self.pending = Some(next),
+            }
+        }
+    }
+}
+
+/// A sorted run spilled to a temporary file, removed when dropped.
+///
+/// Entries are stored as little-endian `u32` key and value lengths followed
+/// by the bytes.
+struct RunFile {
+    path: PathBuf,
+    entries: usize,
+}
+
+impl RunFile {
+    fn write(dir: &Path, pairs: &[Pair]) -> Result<Self> {
+        static NEXT_RUN: AtomicU64 = AtomicU64::new(0);
+        let run = NEXT_RUN.fetch_add(1, atomic::Ordering::Relaxed);
+        let path = dir.join(format!("mdbx-bulk-{}-{}.run", process::id(), run));
+        let file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+            .map_err(io_error)?;
+        let run = RunFile {
+            path,
+            entries: pairs.len(),
+        };
+        let mut writer = BufWriter::new(file);
+        for (key, data) in pairs {
+            writer.write_all(&len_prefix(key)?).map_err(io_error)?;
+            writer.write_all(&len_prefix(data)?).map_err(io_error)?;
+            writer.write_all(key).map_err(io_error)?;
+            writer.write_all(data).map_err(io_error)?;
+        }
+        writer.flush().map_err(io_error)?;
+        Ok(run)
+    }
+
+    fn open(self) -> Result<RunReader> {
+        let file = File::open(&self.path).map_err(io_error)?;
+        Ok(RunReader {
+            reader: BufReader::new(file),
+            remaining: self.entries,
+            _file: self,
+        })
+    }
+}
+
+impl Drop for RunFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+fn len_prefix(bytes: &[u8]) -> Result<[u8; 4]> {
+    u32::try_from(bytes.len())
+        .map(u32::to_le_bytes)
+        .map_err(|_| Error::from_code(MDBX_BAD_VALSIZE))
+}
+
+struct RunReader {
+    reader: BufReader<File>,
+    remaining: usize,
+    _file: RunFile,
+}
+
+impl RunReader {
+    fn next(&mut self) -> Result<Option<Pair>> {
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+        self.remaining -= 1;
+        let mut lens = [0u8; 8];
+        self.reader.read_exact(&mut lens).map_err(io_error)?;
+        let key_len = u32::from_le_bytes(lens[..4].try_into().unwrap()) as usize;
+        let data_len = u32::from_le_bytes(lens[4..].try_into().unwrap()) as usize;
+        let mut key = vec![0; key_len];
+        let mut data = vec![0; data_len];
+        self.reader.read_exact(&mut key).map_err(io_error)?;
+        self.reader.read_exact(&mut data).map_err(io_error)?;
+        Ok(Some((key, data)))
+    }
+}
+
+/// K-way merge of sorted runs. On equal pairs the earlier run comes first.
+struct Merge<F> {
+    runs: Vec<RunReader>,
+    heap: BinaryHeap<Head<F>>,
+}
+
+impl<F: ByteCmp> Merge<F> {
+    fn new(runs: Vec<RunFile>, order: PairOrder<F>) -> Result<Self> {
+        let mut merge = Merge {
+            runs: runs.into_iter().map(RunFile::open).collect::<Result<_>>()?,
+            heap: BinaryHeap::new(),
+        };
+        for run in 0..merge.runs.len() {
+            merge.refill(run, order)?;
+        }
+        Ok(merge)
+    }
+
+    fn refill(&mut self, run: usize, order: PairOrder<F>) -> Result<()> {
+        if let Some(pair) = self.runs[run].next()? {
+            self.heap.push(Head { pair, run, order });
+        }
+        Ok(())
+    }
+}
+
+impl<F: ByteCmp> Iterator for Merge<F> {
+    type Item = Result<Pair>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let Head { pair, run, order } = self.heap.pop()?;
+        match self.refill(run, order) {
+            Ok(()) => Some(Ok(pair)),
+            Err(err) => {
+                self.heap.clear();
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+/// Next entry of a run, ordered so `BinaryHeap` pops the smallest first.
+struct Head<F> {
+    pair: Pair,
+    run: usize,
+    order: PairOrder<F>,
+}
+
+impl<F: ByteCmp> Ord for Head<F> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.order
+            .compare((&other.pair.0, &other.pair.1), (&self.pair.0, &self.pair.1))
+            .then(other.run.cmp(&self.run))
+    }
+}
+
+impl<F: ByteCmp> PartialOrd for Head<F> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<F: ByteCmp> PartialEq for Head<F> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+
+impl<F: ByteCmp> Eq for Head<F> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TestEnv;
+
+    type Cmp = fn(&[u8], &[u8]) -> Ordering;
+
+    fn order(dupsort: bool) -> PairOrder<Cmp> {
+        let cmp: Cmp = |a, b| a.cmp(b);
+        PairOrder {
+            key: cmp,
+            data: dupsort.then_some(cmp),
+        }
+    }
+
+    fn pair(key: &str, data: &str) -> Pair {
+        (key.as_bytes().to_vec(), data.as_bytes().to_vec())
+    }
+
+    #[test]
+    fn test_check_order() {
+        let prev = pair("b", "2");
+        let plain = order(false);
+        assert!(check_order(plain, None, (b"a", b""), 0).is_ok());
+        assert!(check_order(plain, Some(&prev), (b"c", b"1"), 1).is_ok());
+        assert_eq!(
+            check_order(plain, Some(&prev), (b"b", b"3"), 7),
+            Err(Error::OutOfOrder {
+                index: 7,
+                key: b"b".to_vec()
+            })
+        );
+
+        let dupsort = order(true);
+        assert!(check_order(dupsort, Some(&prev), (b"b", b"3"), 1).is_ok());
+        assert!(check_order(dupsort, Some(&prev), (b"b", b"2"), 1).is_err());
+        assert!(check_order(dupsort, Some(&prev), (b"a", b"9"), 1).is_err());
+    }
+
+    #[test]
+    fn test_sort_and_dedup_keep_last() {
+        let order = order(false);
+        let mut pairs = vec![pair("b", "1"), pair("a", "1"), pair("b", "2")];
+        order.sort(&mut pairs);
+        let deduped: Vec<_> = Dedup::new(pairs.into_iter().map(Ok), order)
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(deduped, vec![pair("a", "1"), pair("b", "2")]);
+    }
+
+    #[test]
+    fn test_merge_runs() {
+        let order = order(false);
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+        let first = RunFile::write(dir, &[pair("a", "1"), pair("c", "1")]).unwrap();
+        let second = RunFile::write(dir, &[pair("b", "2"), pair("c", "2")]).unwrap();
+        let paths = [first.path.clone(), second.path.clone()];
+
+        let merge = Merge::new(vec![first, second], order).unwrap();
+        let merged: Vec<_> = Dedup::new(merge, order).collect::<Result<_>>().unwrap();
+        assert_eq!(merged, vec![pair("a", "1"), pair("b", "2"), pair("c", "2")]);
+        assert!(paths.iter().all(|path| !path.exists()));
+    }
+
+    fn create_db(env: &Environment, name: &str, flags: DbFlags) -> Database {
+        let txn = env.begin_rw_txn().unwrap();
+        let db = txn.create_db(Some(name), flags).unwrap();
+        txn.commit().unwrap();
+        db
+    }
+
+    fn read_all(env: &Environment, db: Database) -> Vec<Pair> {
+        let txn = env.begin_ro_txn().unwrap();
+        let pairs = txn
+            .iter(db)
+            .unwrap()
+            .map(|item| {
+                let (key, data) = item.unwrap();
+                (key.to_vec(), data.to_vec())
+            })
+            .collect();
+        pairs
+    }
+
+    #[test]
+    fn test_load_in_chunks() {
+        let env = TestEnv::new();
+        let pairs: Vec<_> = (0..5000u32)
+            .map(|i| (i.to_be_bytes(), i.to_le_bytes()))
+            .collect();
+
+        // Every dirty-space check exceeds one byte
+        let chunked = create_db(&env, "chunked", DbFlags::empty());
+        let stats = BulkLoader::new(&env, chunked)
+            .commit_every(1)
+            .load(pairs.iter().copied())
+            .unwrap();
+        assert_eq!(stats.entries, 5000);
+        assert_eq!(stats.bytes, 5000 * 8);
+        assert_eq!(stats.commits, 5);
+        assert_eq!(stats.runs, 0);
+
+        let whole = create_db(&env, "whole", DbFlags::empty());
+        let stats = BulkLoader::new(&env, whole)
+            .load(pairs.iter().copied())
+            .unwrap();
+        assert_eq!(stats.commits, 1);
+
+        let expected: Vec<_> = pairs
+            .iter()
+            .map(|(key, data)| (key.to_vec(), data.to_vec()))
+            .collect();
+        assert_eq!(read_all(&env, chunked), expected);
+        assert_eq!(read_all(&env, whole), expected);
+    }
+
+    #[test]
+    fn test_load_dupsort() {
+        let env = TestEnv::new();
+        let db = create_db(&env, "dups", DbFlags::DUPSORT);
+        let pairs = [
+            pair("a", "1"),
+            pair("a", "2"),
+            pair("b", "1"),
+            pair("b", "3"),
+        ];
+        let stats = BulkLoader::new(&env, db).load(pairs.clone()).unwrap();
+        assert_eq!(stats.entries, 4);
+        assert_eq!(read_all(&env, db), pairs);
+
+        // Appending a value to the last key continues its duplicates
+        BulkLoader::new(&env, db).load([pair("b", "4")]).unwrap();
+        assert_eq!(read_all(&env, db).last(), Some(&pair("b", "4")));
+        let err = BulkLoader::new(&env, db)
+            .load([pair("b", "2")])
+            .unwrap_err();
+        assert_eq!(
+            err,
+            Error::OutOfOrder {
+                index: 0,
+                key: b"b".to_vec()
+            }
+        );
+    }
+
+    #[test]
+    fn test_load_out_of_order() {
+        let env = TestEnv::new();
+        let db = create_db(&env, "t", DbFlags::empty());
+        BulkLoader::new(&env, db).load([pair("m", "1")]).unwrap();
+
+        // Before the keys already in the table
+        let err = BulkLoader::new(&env, db)
+            .load([pair("a", "1")])
+            .unwrap_err();
+        assert_eq!(
+            err,
+            Error::OutOfOrder {
+                index: 0,
+                key: b"a".to_vec()
+            }
+        );
+        // Within the input; the unfinished chunk is aborted
+        let err = BulkLoader::new(&env, db)
+            .load([pair("x", "1"), pair("w", "1")])
+            .unwrap_err();
+        assert_eq!(
+            err,
+            Error::OutOfOrder {
+                index: 1,
+                key: b"w".to_vec()
+            }
+        );
+        assert_eq!(read_all(&env, db), [pair("m", "1")]);
+    }
+
+    #[test]
+    fn test_load_unsorted_spills_runs() {
+        let env = TestEnv::new();
+        let dir = tempfile::tempdir().unwrap();
+        let db = create_db(&env, "t", DbFlags::empty());
+        // Every key twice, in scrambled order; the second value wins
+        let pairs = (0..400u32).map(|i| {
+            let key = (i * 7919) % 200;
+            (key.to_be_bytes(), [(i >= 200) as u8])
+        });
+        let stats = BulkLoader::new(&env, db)
+            .sort_buffer(512)
+            .temp_dir(dir.path())
+            .load_unsorted(pairs)
+            .unwrap();
+        assert!(stats.runs > 1, "{:?}", stats);
+        assert_eq!(stats.entries, 200);
+
+        let expected: Vec<_> = (0..200u32)
+            .map(|key| (key.to_be_bytes().to_vec(), vec![1]))
+            .collect();
+        assert_eq!(read_all(&env, db), expected);
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 0);
+
+        // Small inputs are sorted in memory
+        let db = create_db(&env, "small", DbFlags::empty());
+        let stats = BulkLoader::new(&env, db)
+            .temp_dir(dir.path())
+            .load_unsorted([pair("b", "1"), pair("a", "1"), pair("b", "2")])
+            .unwrap();
+        assert_eq!(stats.runs, 0);
+        assert_eq!(read_all(&env, db), [pair("a", "1"), pair("b", "2")]);
+    }
+}
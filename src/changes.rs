@@ -0,0 +1,330 @@
+//! Change capture: the tables and keys touched by each committed write.
+
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex, PoisonError};
+
+use crate::database::Database;
+use crate::environment::Environment;
+
+/// Keys a transaction records before falling back to tables only.
+pub const MAX_CAPTURED_KEYS: usize = 100_000;
+
+/// What a committed write transaction changed.
+///
+/// Tables and keys are sorted and deduplicated. A table cleared with
+/// [`RwTxn::clear_db`](crate::RwTxn::clear_db) or dropped with
+/// [`RwTxn::drop_db`](crate::RwTxn::drop_db) is listed in `tables` without
+/// its keys. A transaction that changes more than [`MAX_CAPTURED_KEYS`] keys
+/// reports only its tables, with `keys_truncated` set.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommitEvent {
+    /// Id of the committed transaction.
+    pub txnid: u64,
+    /// Tables with at least one change.
+    pub tables: Vec<Database>,
+    /// Keys that were written or deleted.
+    pub keys: Vec<(Database, Vec<u8>)>,
+    /// Whether `keys` was left empty because there were too many; any key
+    /// of `tables` may have changed.
+    pub keys_truncated: bool,
+}
+
+type Callback = Arc<dyn Fn(&CommitEvent) + Send + Sync>;
+
+#[derive(Default)]
+struct Subscribers {
+    callbacks: Vec<Callback>,
+    channels: Vec<Sender<Arc<CommitEvent>>>,
+}
+
+/// Subscribers to the commits of an environment.
+///
+/// Events are delivered on the committing thread once `mdbx_txn_commit`
+/// succeeds; aborted transactions and commits that changed nothing are not
+/// reported.
+///
+/// ```rust,ignore
+/// let env = Environment::builder().capture_changes(true).open("./mydb")?;
+/// let feed = env.change_feed().unwrap();
+/// feed.subscribe(|event| cache.invalidate(&event.keys));
+/// let events = feed.channel();
+/// ```
+#[derive(Default)]
+pub struct ChangeFeed {
+    subscribers: Mutex<Subscribers>,
+}
+
+impl ChangeFeed {
+    /// Call `f` with every event.
+    ///
+    /// Callbacks run after the commit, so a panic propagates from
+    /// [`RwTxn::commit`](crate::RwTxn::commit) without undoing it.
+    pub fn subscribe(&self, f: impl Fn(&CommitEvent) + Send + Sync + 'static) {
+        self.lock().callbacks.push(Arc::new(f));
+    }
+
+    /// Receive every event on a channel, until the receiver is dropped.
+    pub fn channel(&self) -> Receiver<Arc<CommitEvent>> {
+        let (sender, receiver) = mpsc::channel();
+        self.lock().channels.push(sender);
+        receiver
+    }
+
+    pub(crate) fn deliver(&self, event: CommitEvent) {
+        let event = Arc::new(event);
+        let callbacks = {
+            let mut subscribers = self.lock();
+            subscribers
+                .channels
+                .retain(|channel| channel.send(Arc::clone(&event)).is_ok());
+            subscribers.callbacks.clone()
+        };
+        // Called without the lock, so callbacks may subscribe
+        for callback in callbacks {
+            callback(&event);
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Subscribers> {
+        self.subscribers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl fmt::Debug for ChangeFeed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let subscribers = self.lock();
+        f.debug_struct("ChangeFeed")
+            .field("callbacks", &subscribers.callbacks.len())
+            .field("channels", &subscribers.channels.len())
+            .finish()
+    }
+}
+
+/// Changes recorded by one transaction.
+#[derive(Debug, Default)]
+pub(crate) struct ChangeSet {
+    tables: HashSet<Database>,
+    keys: HashSet<(Database, Vec<u8>)>,
+    keys_truncated: bool,
+}
+
+impl ChangeSet {
+    pub(crate) fn table(&mut self, db: Database) {
+        self.tables.insert(db);
+    }
+
+    pub(crate) fn key(&mut self, db: Database, key: &[u8]) {
+        self.tables.insert(db);
+        if !self.keys_truncated {
+            self.keys.insert((db, key.to_vec()));
+            self.check_keys();
+        }
+    }
+
+    fn merge(&mut self, other: ChangeSet) {
+        self.tables.extend(other.tables);
+        self.keys_truncated |= other.keys_truncated;
+        if !self.keys_truncated {
+            self.keys.extend(other.keys);
+        }
+        self.check_keys();
+    }
+
+    /// Drop the keys once there are too many to be worth reporting.
+    fn check_keys(&mut self) {
+        if self.keys_truncated || self.keys.len() > MAX_CAPTURED_KEYS {
+            self.keys = HashSet::new();
+            self.keys_truncated = true;
+        }
+    }
+
+    fn into_event(self, txnid: u64) -> CommitEvent {
+        let mut tables: Vec<_> = self.tables.into_iter().collect();
+        tables.sort_by_key(|db| db.dbi());
+        let mut keys: Vec<_> = self.keys.into_iter().collect();
+        keys.sort_by(|(a, a_key), (b, b_key)| (a.dbi(), a_key).cmp(&(b.dbi(), b_key)));
+        CommitEvent {
+            txnid,
+            tables,
+            keys,
+            keys_truncated: self.keys_truncated,
+        }
+    }
+}
+
+/// Change capture state of a write transaction.
+#[derive(Debug, Default)]
+pub(crate) struct Capture<'env> {
+    changes: RefCell<ChangeSet>,
+    /// Changes of the parent transaction, for nested transactions.
+    parent: Option<&'env RefCell<ChangeSet>>,
+}
+
+impl<'env> Capture<'env> {
+    /// Capture for a child of the transaction capturing with `self`.
+    pub(crate) fn nested(&self) -> Capture<'_> {
+        Capture {
+            changes: RefCell::default(),
+            parent: Some(&self.changes),
+        }
+    }
+
+    pub(crate) fn record(&self, f: impl FnOnce(&mut ChangeSet)) {
+        f(&mut self.changes.borrow_mut());
+    }
+
+    /// Hand the changes on once the transaction `txnid` has committed:
+    /// to the parent transaction, or to the subscribers of `env`.
+    pub(crate) fn committed(self, env: &Environment, txnid: u64) {
+        let changes = self.changes.into_inner();
+        if let Some(parent) = self.parent {
+            parent.borrow_mut().merge(changes);
+        } else if let Some(feed) = env.change_feed() {
+            if !changes.tables.is_empty() {
+                feed.deliver(changes.into_event(txnid));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flags::{DbFlags, PutFlags};
+    use crate::test_util::TestEnv;
+    use crate::transaction::Txn;
+
+    fn db(dbi: u32) -> Database {
+        Database::new(dbi, DbFlags::empty())
+    }
+
+    #[test]
+    fn test_into_event_sorts_and_dedups() {
+        let mut changes = ChangeSet::default();
+        changes.key(db(3), b"b");
+        changes.key(db(2), b"z");
+        changes.key(db(3), b"a");
+        changes.key(db(3), b"b");
+        changes.table(db(1));
+
+        let event = changes.into_event(7);
+        assert_eq!(event.txnid, 7);
+        assert_eq!(event.tables, [db(1), db(2), db(3)]);
+        assert_eq!(
+            event.keys,
+            [
+                (db(2), b"z".to_vec()),
+                (db(3), b"a".to_vec()),
+                (db(3), b"b".to_vec()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut parent = ChangeSet::default();
+        parent.key(db(2), b"a");
+        let mut child = ChangeSet::default();
+        child.key(db(2), b"a");
+        child.table(db(4));
+        parent.merge(child);
+
+        let event = parent.into_event(1);
+        assert_eq!(event.tables, [db(2), db(4)]);
+        assert_eq!(event.keys, [(db(2), b"a".to_vec())]);
+    }
+
+    #[test]
+    fn test_deliver() {
+        let feed = ChangeFeed::default();
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&seen);
+        feed.subscribe(move |event| sink.lock().unwrap().push(event.txnid));
+        let events = feed.channel();
+        drop(feed.channel());
+
+        let mut changes = ChangeSet::default();
+        changes.key(db(2), b"key");
+        feed.deliver(changes.into_event(5));
+
+        assert_eq!(*seen.lock().unwrap(), [5]);
+        assert_eq!(events.try_recv().unwrap().txnid, 5);
+        assert_eq!(feed.lock().channels.len(), 1);
+    }
+
+    #[test]
+    fn test_keys_truncated() {
+        let mut changes = ChangeSet::default();
+        for i in 0..=MAX_CAPTURED_KEYS as u32 {
+            changes.key(db(2), &i.to_be_bytes());
+        }
+        changes.key(db(3), b"after");
+        let event = changes.into_event(1);
+        assert!(event.keys_truncated);
+        assert!(event.keys.is_empty());
+        assert_eq!(event.tables, [db(2), db(3)]);
+
+        let mut parent = ChangeSet::default();
+        parent.key(db(2), b"a");
+        let mut child = ChangeSet::default();
+        child.key(db(4), b"b");
+        child.keys_truncated = true;
+        parent.merge(child);
+        let event = parent.into_event(1);
+        assert!(event.keys_truncated);
+        assert!(event.keys.is_empty());
+        assert_eq!(event.tables, [db(2), db(4)]);
+    }
+
+    #[test]
+    fn test_commit_events() {
+        let env = TestEnv::with(Environment::builder().max_dbs(4).capture_changes(true));
+        let events = env.change_feed().unwrap().channel();
+
+        let txn = env.begin_rw_txn().unwrap();
+        let users = txn.create_db(Some("users"), DbFlags::empty()).unwrap();
+        let posts = txn.create_db(Some("posts"), DbFlags::empty()).unwrap();
+        txn.put(users, b"bob", b"1", PutFlags::empty()).unwrap();
+        txn.put(users, b"alice", b"1", PutFlags::empty()).unwrap();
+        txn.put(posts, b"p1", b"", PutFlags::empty()).unwrap();
+        assert!(txn.del(posts, b"p1", None).unwrap());
+        assert!(!txn.del(posts, b"missing", None).unwrap());
+        let txnid = txn.id();
+        txn.commit().unwrap();
+
+        let event = events.try_recv().unwrap();
+        assert_eq!(event.txnid, txnid);
+        assert_eq!(event.tables, [users, posts]);
+        assert_eq!(
+            event.keys,
+            [
+                (users, b"alice".to_vec()),
+                (users, b"bob".to_vec()),
+                (posts, b"p1".to_vec()),
+            ]
+        );
+        assert!(!event.keys_truncated);
+        assert!(events.try_recv().is_err());
+
+        let txn = env.begin_rw_txn().unwrap();
+        txn.put(users, b"carol", b"1", PutFlags::empty()).unwrap();
+        txn.abort();
+        // Nothing changed
+        env.begin_rw_txn().unwrap().commit().unwrap();
+        assert!(events.try_recv().is_err());
+
+        let txn = env.begin_rw_txn().unwrap();
+        txn.drop_db(posts).unwrap();
+        let txnid = txn.id();
+        txn.commit().unwrap();
+        let event = events.try_recv().unwrap();
+        assert_eq!(event.txnid, txnid);
+        assert_eq!(event.tables, [posts]);
+        assert!(event.keys.is_empty());
+    }
+}
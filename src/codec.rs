@@ -0,0 +1,338 @@
+//! Key and value codecs for typed tables.
+
+use std::borrow::Cow;
+
+use crate::constants::MDBX_BAD_VALSIZE;
+use crate::error::{Error, Result};
+use crate::key::OrderedKey;
+use crate::value::Value;
+
+/// Conversion between a Rust type and the bytes stored in a table.
+///
+/// Codecs are usually the stored type itself (`u64`, `str`, `[u8; 32]`), or
+/// a marker such as `Pod<T>` or `Postcard<T>` when the encoding needs
+/// to be spelled out.
+pub trait Codec {
+    /// Type accepted when writing.
+    type Item: ?Sized;
+
+    /// Encoded bytes, borrowing from the item where possible.
+    type Encoded<'a>: AsRef<[u8]>
+    where
+        Self::Item: 'a;
+
+    /// Type returned when reading, borrowing from the transaction where
+    /// possible.
+    type Decoded<'txn>;
+
+    /// Encode `item`.
+    fn encode(item: &Self::Item) -> Result<Self::Encoded<'_>>;
+
+    /// Decode a value read from the database.
+    fn decode(value: Value<'_>) -> Result<Self::Decoded<'_>>;
+}
+
+/// Codec whose encoding sorts in the same order as its items, so it can be
+/// used for keys with the default comparator.
+pub trait KeyCodec: Codec {}
+
+/// Codec usable for values.
+pub trait ValueCodec: Codec {}
+
+macro_rules! ordered_codec {
+    ($($ty:ty),*) => {$(
+        impl Codec for $ty {
+            type Item = $ty;
+            type Encoded<'a> = <$ty as OrderedKey>::Bytes;
+            type Decoded<'txn> = $ty;
+
+            #[inline]
+            fn encode(item: &$ty) -> Result<Self::Encoded<'_>> {
+                Ok(item.to_key())
+            }
+
+            #[inline]
+            fn decode(value: Value<'_>) -> Result<$ty> {
+                <$ty>::from_key(&value)
+            }
+        }
+
+        impl KeyCodec for $ty {}
+        impl ValueCodec for $ty {}
+    )*};
+}
+
+ordered_codec!(u8, u16, u32, u64, u128, i32, i64, f32, f64);
+
+/// UTF-8 text, borrowed from the memory map when possible.
+impl Codec for str {
+    type Item = str;
+    type Encoded<'a> = &'a [u8];
+    type Decoded<'txn> = Cow<'txn, str>;
+
+    #[inline]
+    fn encode(item: &str) -> Result<&[u8]> {
+        Ok(item.as_bytes())
+    }
+
+    fn decode(value: Value<'_>) -> Result<Cow<'_, str>> {
+        match value {
+            Value::Borrowed(bytes) => std::str::from_utf8(bytes)
+                .map(Cow::Borrowed)
+                .map_err(utf8_error),
+            Value::Owned(bytes) => String::from_utf8(bytes)
+                .map(Cow::Owned)
+                .map_err(|err| utf8_error(err.utf8_error())),
+        }
+    }
+}
+
+impl KeyCodec for str {}
+impl ValueCodec for str {}
+
+/// UTF-8 text, always copied out.
+impl Codec for String {
+    type Item = str;
+    type Encoded<'a> = &'a [u8];
+    type Decoded<'txn> = String;
+
+    #[inline]
+    fn encode(item: &str) -> Result<&[u8]> {
+        Ok(item.as_bytes())
+    }
+
+    fn decode(value: Value<'_>) -> Result<String> {
+        str::decode(value).map(Cow::into_owned)
+    }
+}
+
+impl KeyCodec for String {}
+impl ValueCodec for String {}
+
+fn utf8_error(_: std::str::Utf8Error) -> Error {
+    Error::Codec("invalid UTF-8".to_owned())
+}
+
+/// Raw bytes, borrowed from the memory map when possible.
+impl Codec for [u8] {
+    type Item = [u8];
+    type Encoded<'a> = &'a [u8];
+    type Decoded<'txn> = Value<'txn>;
+
+    #[inline]
+    fn encode(item: &[u8]) -> Result<&[u8]> {
+        Ok(item)
+    }
+
+    #[inline]
+    fn decode(value: Value<'_>) -> Result<Value<'_>> {
+        Ok(value)
+    }
+}
+
+impl KeyCodec for [u8] {}
+impl ValueCodec for [u8] {}
+
+/// Raw bytes, always copied out.
+impl Codec for Vec<u8> {
+    type Item = [u8];
+    type Encoded<'a> = &'a [u8];
+    type Decoded<'txn> = Vec<u8>;
+
+    #[inline]
+    fn encode(item: &[u8]) -> Result<&[u8]> {
+        Ok(item)
+    }
+
+    #[inline]
+    fn decode(value: Value<'_>) -> Result<Vec<u8>> {
+        Ok(value.into_owned())
+    }
+}
+
+impl KeyCodec for Vec<u8> {}
+impl ValueCodec for Vec<u8> {}
+
+/// Fixed-size byte arrays such as hashes.
+impl<const N: usize> Codec for [u8; N] {
+    type Item = [u8; N];
+    type Encoded<'a> = &'a [u8; N];
+    type Decoded<'txn> = [u8; N];
+
+    #[inline]
+    fn encode(item: &[u8; N]) -> Result<&[u8; N]> {
+        Ok(item)
+    }
+
+    fn decode(value: Value<'_>) -> Result<[u8; N]> {
+        (*value)
+            .try_into()
+            .map_err(|_| Error::from_code(MDBX_BAD_VALSIZE))
+    }
+}
+
+impl<const N: usize> KeyCodec for [u8; N] {}
+impl<const N: usize> ValueCodec for [u8; N] {}
+
+/// Plain-old-data values stored in native layout with `bytemuck`.
+///
+/// Decoding borrows straight from the memory map when the value is suitably
+/// aligned and copies it otherwise. Native layout doesn't sort by value, so
+/// this is a [`ValueCodec`] only.
+#[cfg(feature = "bytemuck")]
+#[derive(Debug)]
+pub struct Pod<T>(std::marker::PhantomData<T>);
+
+#[cfg(feature = "bytemuck")]
+impl<T: bytemuck::Pod> Codec for Pod<T> {
+    type Item = T;
+    type Encoded<'a> = &'a [u8];
+    type Decoded<'txn> = PodRef<'txn, T>;
+
+    #[inline]
+    fn encode(item: &T) -> Result<&[u8]> {
+        Ok(bytemuck::bytes_of(item))
+    }
+
+    fn decode(value: Value<'_>) -> Result<PodRef<'_, T>> {
+        if value.len() != std::mem::size_of::<T>() {
+            return Err(Error::from_code(MDBX_BAD_VALSIZE));
+        }
+        match value {
+            Value::Borrowed(bytes) => Ok(match bytemuck::try_from_bytes(bytes) {
+                Ok(item) => PodRef::Borrowed(item),
+                Err(_) => PodRef::Owned(bytemuck::pod_read_unaligned(bytes)),
+            }),
+            Value::Owned(bytes) => Ok(PodRef::Owned(bytemuck::pod_read_unaligned(&bytes))),
+        }
+    }
+}
+
+#[cfg(feature = "bytemuck")]
+impl<T: bytemuck::Pod> ValueCodec for Pod<T> {}
+
+/// A [`Pod`] value, borrowed from the memory map or copied.
+#[cfg(feature = "bytemuck")]
+#[derive(Debug, Clone, Copy)]
+pub enum PodRef<'txn, T> {
+    Borrowed(&'txn T),
+    Owned(T),
+}
+
+#[cfg(feature = "bytemuck")]
+impl<T: Copy> PodRef<'_, T> {
+    /// Copy the value out.
+    pub fn get(&self) -> T {
+        **self
+    }
+}
+
+#[cfg(feature = "bytemuck")]
+impl<T> std::ops::Deref for PodRef<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        match self {
+            PodRef::Borrowed(item) => item,
+            PodRef::Owned(item) => item,
+        }
+    }
+}
+
+/// Values serialized with `serde` in the compact `postcard` format.
+///
+/// The encoding doesn't sort by value, so this is a [`ValueCodec`] only.
+#[cfg(feature = "serde")]
+#[derive(Debug)]
+pub struct Postcard<T>(std::marker::PhantomData<T>);
+
+#[cfg(feature = "serde")]
+impl<T: serde::Serialize + serde::de::DeserializeOwned> Codec for Postcard<T> {
+    type Item = T;
+    type Encoded<'a>
+        = Vec<u8>
+    where
+        T: 'a;
+    type Decoded<'txn> = T;
+
+    fn encode(item: &T) -> Result<Vec<u8>> {
+        postcard::to_allocvec(item).map_err(|err| Error::Codec(err.to_string()))
+    }
+
+    fn decode(value: Value<'_>) -> Result<T> {
+        postcard::from_bytes(&value).map_err(|err| Error::Codec(err.to_string()))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<T: serde::Serialize + serde::de::DeserializeOwned> ValueCodec for Postcard<T> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roundtrip<C: Codec + ?Sized>(item: &C::Item) -> Result<C::Decoded<'static>> {
+        let bytes = C::encode(item)?.as_ref().to_vec();
+        C::decode(Value::Owned(bytes))
+    }
+
+    #[test]
+    fn test_ordered_codecs() {
+        assert_eq!(u64::encode(&1).unwrap(), [0, 0, 0, 0, 0, 0, 0, 1]);
+        assert_eq!(roundtrip::<u64>(&u64::MAX), Ok(u64::MAX));
+        assert_eq!(roundtrip::<u16>(&513), Ok(513));
+        assert_eq!(roundtrip::<u128>(&7), Ok(7));
+        assert!(u8::encode(&1).unwrap() < u8::encode(&2).unwrap());
+        assert_eq!(
+            u32::decode(Value::Borrowed(&[1, 2])),
+            Err(Error::from_code(MDBX_BAD_VALSIZE))
+        );
+    }
+
+    #[test]
+    fn test_text_and_byte_codecs() {
+        let decoded = str::decode(Value::Borrowed(b"hello")).unwrap();
+        assert!(matches!(decoded, Cow::Borrowed("hello")));
+        assert_eq!(roundtrip::<String>("héllo"), Ok("héllo".to_owned()));
+        assert!(matches!(
+            str::decode(Value::Borrowed(b"\xff")),
+            Err(Error::Codec(_))
+        ));
+        assert_eq!(roundtrip::<Vec<u8>>(b"\x00\x01"), Ok(vec![0, 1]));
+        assert_eq!(roundtrip::<[u8; 3]>(&[1, 2, 3]), Ok([1, 2, 3]));
+        assert_eq!(
+            <[u8; 4]>::decode(Value::Borrowed(b"abc")),
+            Err(Error::from_code(MDBX_BAD_VALSIZE))
+        );
+    }
+
+    #[cfg(feature = "bytemuck")]
+    #[test]
+    fn test_pod_codec() {
+        let items = [0x0102_0304u32, 7];
+        let bytes = bytemuck::bytes_of(&items);
+        let first = Pod::<u32>::decode(Value::Borrowed(&bytes[..4])).unwrap();
+        assert!(matches!(first, PodRef::Borrowed(_)));
+        assert_eq!(first.get(), 0x0102_0304);
+
+        let mut unaligned = [0u8; 5];
+        unaligned[1..].copy_from_slice(&bytes[4..]);
+        let second = Pod::<u32>::decode(Value::Borrowed(&unaligned[1..])).unwrap();
+        assert_eq!(*second, 7);
+        assert!(Pod::<u32>::decode(Value::Borrowed(&bytes[..3])).is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_postcard_codec() {
+        let item = (42u32, "name".to_owned(), vec![1u8, 2]);
+        assert_eq!(
+            roundtrip::<Postcard<(u32, String, Vec<u8>)>>(&item),
+            Ok(item)
+        );
+        assert!(matches!(
+            Postcard::<(u32, String)>::decode(Value::Borrowed(b"\xff")),
+            Err(Error::Codec(_))
+        ));
+    }
+}
@@ -0,0 +1,210 @@
+//! Safe cursors.
+
+use std::fmt;
+use std::ptr;
+
+use crate::database::Database;
+use crate::error::{mdbx_result, Error, Result};
+use crate::flags::PutFlags;
+use crate::transaction::{fill_reserved, value_from_val, RwTxn, Txn};
+use crate::value::Value;
+use crate::*;
+
+/// A key/value pair read through a cursor.
+pub type Item<'txn> = (Value<'txn>, Value<'txn>);
+
+/// A cursor over one database.
+///
+/// The cursor is closed when dropped.
+pub struct Cursor<'txn, T: Txn + ?Sized> {
+    cursor: *mut MDBX_cursor,
+    txn: &'txn T,
+    db: Database,
+}
+
+impl<'txn, T: Txn + ?Sized> Cursor<'txn, T> {
+    pub(crate) fn open(txn: &'txn T, db: Database) -> Result<Self> {
+        let mut cursor = ptr::null_mut();
+        mdbx_result(unsafe { mdbx_cursor_open(txn.as_ptr(), db.dbi(), &mut cursor) })?;
+        Ok(Cursor { cursor, txn, db })
+    }
+
+    /// Take ownership of `cursor`, which must be bound to `txn` and `db`.
+    pub(crate) unsafe fn from_raw(txn: &'txn T, db: Database, cursor: *mut MDBX_cursor) -> Self {
+        Cursor { cursor, txn, db }
+    }
+
+    /// Give up ownership of the handle without closing it.
+    pub(crate) fn into_raw(self) -> *mut MDBX_cursor {
+        std::mem::ManuallyDrop::new(self).cursor
+    }
+
+    /// The database this cursor iterates.
+    #[inline]
+    pub fn db(&self) -> Database {
+        self.db
+    }
+
+    /// The transaction this cursor belongs to.
+    #[inline]
+    pub fn txn(&self) -> &'txn T {
+        self.txn
+    }
+
+    /// Raw cursor handle for use with the FFI functions.
+    #[inline]
+    pub fn as_ptr(&self) -> *mut MDBX_cursor {
+        self.cursor
+    }
+
+    /// Position at the first entry.
+    pub fn first(&mut self) -> Result<Option<Item<'txn>>> {
+        self.get(MDBX_cursor_op::MDBX_FIRST, None, None)
+    }
+
+    /// Position at the last entry.
+    pub fn last(&mut self) -> Result<Option<Item<'txn>>> {
+        self.get(MDBX_cursor_op::MDBX_LAST, None, None)
+    }
+
+    /// Move to the next entry.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Result<Option<Item<'txn>>> {
+        self.get(MDBX_cursor_op::MDBX_NEXT, None, None)
+    }
+
+    /// Move to the previous entry.
+    pub fn prev(&mut self) -> Result<Option<Item<'txn>>> {
+        self.get(MDBX_cursor_op::MDBX_PREV, None, None)
+    }
+
+    /// The entry at the current position.
+    pub fn current(&mut self) -> Result<Option<Item<'txn>>> {
+        self.get(MDBX_cursor_op::MDBX_GET_CURRENT, None, None)
+    }
+
+    /// Position at `key`, or return `None` if it doesn't exist.
+    pub fn set(&mut self, key: &[u8]) -> Result<Option<Item<'txn>>> {
+        self.get(MDBX_cursor_op::MDBX_SET_KEY, Some(key), None)
+    }
+
+    /// Position at the first key not ordered before `key`.
+    pub fn lower_bound(&mut self, key: &[u8]) -> Result<Option<Item<'txn>>> {
+        self.get(MDBX_cursor_op::MDBX_SET_LOWERBOUND, Some(key), None)
+    }
+
+    /// Run a cursor operation.
+    ///
+    /// `MDBX_RESULT_TRUE` (an inexact match for the bound-seeking operations)
+    /// counts as success, `MDBX_NOTFOUND` yields `None`.
+    pub(crate) fn get(
+        &mut self,
+        op: MDBX_cursor_op,
+        key: Option<&[u8]>,
+        data: Option<&[u8]>,
+    ) -> Result<Option<Item<'txn>>> {
+        let mut key_val = key.map(bytes_to_val).unwrap_or_default();
+        let mut data_val = data.map(bytes_to_val).unwrap_or_default();
+        let (key_in, data_in) = (key_val.iov_base, data_val.iov_base);
+        match unsafe { mdbx_cursor_get(self.cursor, &mut key_val, &mut data_val, op) } {
+            MDBX_SUCCESS | MDBX_RESULT_TRUE => {}
+            MDBX_NOTFOUND => return Ok(None),
+            rc => return Err(Error::from_code(rc)),
+        }
+        Ok(Some((
+            self.value(&key_val, key_in)?,
+            self.value(&data_val, data_in)?,
+        )))
+    }
+
+    /// Wrap a value, copying it if the engine left it pointing at our input.
+    fn value(&self, val: &MDBX_val, input: *mut c_void) -> Result<Value<'txn>> {
+        if !input.is_null() && val.iov_base == input {
+            return Ok(Value::Owned(unsafe { val_to_bytes(val) }.to_vec()));
+        }
+        unsafe { value_from_val(self.txn, val) }
+    }
+}
+
+impl<'env> Cursor<'_, RwTxn<'env>> {
+    /// Store `data` under `key`, leaving the cursor on the new entry.
+    pub fn put(&mut self, key: &[u8], data: &[u8], flags: PutFlags) -> Result<()> {
+        flags.validate_for(self.db.flags())?;
+        let key_val = bytes_to_val(key);
+        let mut data = bytes_to_val(data);
+        mdbx_result(unsafe { mdbx_cursor_put(self.cursor, &key_val, &mut data, flags.bits()) })?;
+        let db = self.db;
+        self.txn.record(|changes| changes.key(db, key));
+        Ok(())
+    }
+}
+
+/// A write cursor holding the only borrow of its transaction.
+///
+/// Returned by [`RwTxn::cursor_mut`]. Reserved buffers are only handed out
+/// through this cursor, so nothing else can read the transaction meanwhile.
+#[derive(Debug)]
+pub struct CursorMut<'txn, 'env> {
+    cursor: Cursor<'txn, RwTxn<'env>>,
+}
+
+impl<'txn, 'env> CursorMut<'txn, 'env> {
+    pub(crate) fn open(txn: &'txn mut RwTxn<'env>, db: Database) -> Result<Self> {
+        Ok(CursorMut {
+            cursor: Cursor::open(txn, db)?,
+        })
+    }
+
+    /// The database this cursor writes.
+    #[inline]
+    pub fn db(&self) -> Database {
+        self.cursor.db
+    }
+
+    /// Store `data` under `key`, leaving the cursor on the new entry.
+    pub fn put(&mut self, key: &[u8], data: &[u8], flags: PutFlags) -> Result<()> {
+        self.cursor.put(key, data, flags)
+    }
+
+    /// Reserve `len` bytes for `key` with `MDBX_RESERVE` and let `f` fill
+    /// them in place, leaving the cursor on the new entry.
+    ///
+    /// See [`RwTxn::reserve`].
+    pub fn reserve<R>(
+        &mut self,
+        key: &[u8],
+        len: usize,
+        f: impl FnOnce(&mut [u8]) -> R,
+    ) -> Result<R> {
+        let cursor = &mut self.cursor;
+        PutFlags::RESERVE.validate_for(cursor.db.flags())?;
+        let key_val = bytes_to_val(key);
+        let mut data = MDBX_val {
+            iov_base: ptr::null_mut(),
+            iov_len: len,
+        };
+        mdbx_result(unsafe {
+            mdbx_cursor_put(cursor.cursor, &key_val, &mut data, PutFlags::RESERVE.bits())
+        })?;
+        let db = cursor.db;
+        cursor.txn.record(|changes| changes.key(db, key));
+        Ok(unsafe { fill_reserved(&data, f) })
+    }
+}
+
+impl<T: Txn + ?Sized> fmt::Debug for Cursor<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Cursor")
+            .field("cursor", &self.cursor)
+            .field("db", &self.db)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T: Txn + ?Sized> Drop for Cursor<'_, T> {
+    fn drop(&mut self) {
+        unsafe {
+            mdbx_cursor_close(self.cursor);
+        }
+    }
+}
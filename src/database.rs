@@ -0,0 +1,50 @@
+//! Database (table) handles.
+
+use crate::error::{mdbx_result, Result};
+use crate::flags::DbFlags;
+use crate::info::Stat;
+use crate::transaction::Txn;
+use crate::{mdbx_dbi_stat, MDBX_dbi, MDBX_stat};
+
+/// Handle to a named or the main database within an environment.
+///
+/// Handles are obtained from [`Txn::open_db`](crate::Txn::open_db) or
+/// [`RwTxn::create_db`](crate::RwTxn::create_db) and stay valid until the
+/// environment is closed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Database {
+    dbi: MDBX_dbi,
+    flags: DbFlags,
+}
+
+impl Database {
+    pub(crate) fn new(dbi: MDBX_dbi, flags: DbFlags) -> Self {
+        Database { dbi, flags }
+    }
+
+    /// Raw database handle for use with the FFI functions.
+    #[inline]
+    pub fn dbi(&self) -> MDBX_dbi {
+        self.dbi
+    }
+
+    /// Flags the database was created with (`DUPSORT`, `REVERSEKEY`, ...).
+    #[inline]
+    pub fn flags(&self) -> DbFlags {
+        self.flags
+    }
+
+    /// B-tree statistics of this database as seen by `txn`.
+    pub fn stat(&self, txn: &impl Txn) -> Result<Stat> {
+        let mut stat = MDBX_stat::default();
+        mdbx_result(unsafe {
+            mdbx_dbi_stat(
+                txn.as_ptr(),
+                self.dbi,
+                &mut stat,
+                std::mem::size_of::<MDBX_stat>(),
+            )
+        })?;
+        Ok(stat.into())
+    }
+}
@@ -0,0 +1,714 @@
+//! Safe environment handle.
+
+use std::ffi::CString;
+use std::os::raw::c_int;
+use std::path::Path;
+use std::ptr;
+use std::sync::Arc;
+use std::thread;
+
+use crate::changes::ChangeFeed;
+use crate::constants::{MDBX_EINVAL, MDBX_MAP_FULL};
+use crate::error::{mdbx_result, Error, Result};
+use crate::flags::{EnvFlags, SyncMode, TxnFlags};
+use crate::geometry::Geometry;
+use crate::growth::{MapGrowth, MapGrowthStats};
+use crate::info::{EnvInfo, Stat};
+use crate::latency::CommitLatencyStats;
+use crate::options::{EnvOption, EnvOptionKind, EnvOptions};
+use crate::registry;
+use crate::retry::RetryPolicy;
+use crate::snapshot::Snapshot;
+use crate::trace;
+use crate::transaction::{RoTxn, RwTxn};
+use crate::version::check_engine_version;
+use crate::*;
+
+/// An open MDBX environment.
+///
+/// The environment is closed when dropped.
+#[derive(Debug)]
+pub struct Environment {
+    env: *mut MDBX_env,
+    commit_latency: Option<CommitLatencyStats>,
+    change_feed: Option<ChangeFeed>,
+    map_growth: Option<MapGrowth>,
+    map_growth_stats: MapGrowthStats,
+    retry: RetryPolicy,
+    /// Id in the process-wide registry, once registered.
+    registration: Option<u64>,
+}
+
+// SAFETY: the engine synchronizes access to the environment internally.
+unsafe impl Send for Environment {}
+unsafe impl Sync for Environment {}
+
+impl Environment {
+    /// Open the environment at `path` with default geometry and limits.
+    ///
+    /// Use [`Environment::builder`] to configure the environment first.
+    pub fn open(
+        path: impl AsRef<Path>,
+        flags: EnvFlags,
+        sync_mode: SyncMode,
+        mode: mdbx_mode_t,
+    ) -> Result<Self> {
+        Environment::builder()
+            .flags(flags)
+            .sync_mode(sync_mode)
+            .mode(mode)
+            .open(path)
+    }
+
+    /// Configure a new environment.
+    pub fn builder() -> EnvironmentBuilder {
+        EnvironmentBuilder::default()
+    }
+
+    /// Begin a read-only transaction.
+    pub fn begin_ro_txn(&self) -> Result<RoTxn<'_>> {
+        RoTxn::begin(self)
+    }
+
+    /// Begin a [`Snapshot`] that can be shared across threads and tasks.
+    pub fn snapshot(self: &Arc<Self>) -> Result<Snapshot> {
+        Snapshot::new(Arc::clone(self))
+    }
+
+    /// Begin a read-write transaction, waiting for the writer lock.
+    pub fn begin_rw_txn(&self) -> Result<RwTxn<'_>> {
+        RwTxn::begin(self, TxnFlags::empty())
+    }
+
+    /// Begin a read-write transaction with extra flags.
+    ///
+    /// With `MDBX_TXN_TRY` this fails with `MDBX_BUSY` instead of waiting for
+    /// the writer lock.
+    pub fn begin_rw_txn_with(&self, flags: TxnFlags) -> Result<RwTxn<'_>> {
+        if flags.intersects(TxnFlags::RDONLY | TxnFlags::RDONLY_PREPARE) {
+            return Err(Error::InvalidFlags(
+                "MDBX_TXN_RDONLY is not allowed for a write transaction",
+            ));
+        }
+        RwTxn::begin(self, flags)
+    }
+
+    /// Run `f` in a read-only transaction.
+    ///
+    /// Transient errors are retried as configured with
+    /// [`EnvironmentBuilder::retry_policy`], running `f` again.
+    pub fn read<R>(&self, mut f: impl FnMut(&RoTxn<'_>) -> Result<R>) -> Result<R> {
+        let mut retry = 0;
+        loop {
+            match self.begin_ro_txn().and_then(|txn| f(&txn)) {
+                Err(err) if RetryPolicy::is_retryable(&err) => self.backoff(retry, err)?,
+                result => return result,
+            }
+            retry += 1;
+        }
+    }
+
+    /// Run `f` in a write transaction and commit it, aborting if `f` fails.
+    ///
+    /// Transient errors are retried as configured with
+    /// [`EnvironmentBuilder::retry_policy`]. With a [`MapGrowth`] policy, a
+    /// transaction failing with `MDBX_MAP_FULL` is aborted, the map grown
+    /// and `f` run again, counting towards the retries of later errors.
+    /// Either way `f` must be safe to replay.
+    pub fn write<R>(&self, mut f: impl FnMut(&RwTxn<'_>) -> Result<R>) -> Result<R> {
+        let flags = if self.retry.is_try_writer() {
+            TxnFlags::TRY
+        } else {
+            TxnFlags::empty()
+        };
+        let mut retry = 0;
+        loop {
+            let result = RwTxn::begin(self, flags).and_then(|txn| {
+                let value = f(&txn)?;
+                txn.commit().map(|()| value)
+            });
+            match result {
+                Err(Error::Mdbx(MDBX_MAP_FULL)) if self.grow_map()? => {}
+                Err(err) if RetryPolicy::is_retryable(&err) => self.backoff(retry, err)?,
+                result => return result,
+            }
+            retry += 1;
+        }
+    }
+
+    /// Sleep before retry number `retry`, or give up with `err`.
+    fn backoff(&self, retry: u32, err: Error) -> Result<()> {
+        let delay = self.retry.delay(retry).ok_or(err)?;
+        thread::sleep(delay);
+        Ok(())
+    }
+
+    /// Raise the upper size bound as the growth policy allows.
+    ///
+    /// Returns `false` without a policy, once its limit is reached, or if the
+    /// engine's rounding left the bound where it was.
+    fn grow_map(&self) -> Result<bool> {
+        let Some(growth) = self.map_growth else {
+            return Ok(false);
+        };
+        let old_upper = self.info()?.geometry.upper;
+        let Some(upper) = growth.next_upper(old_upper) else {
+            return Ok(false);
+        };
+        mdbx_result(unsafe {
+            mdbx_env_set_geometry(self.env, -1, -1, upper as isize, -1, -1, -1)
+        })?;
+        let upper = self.info()?.geometry.upper;
+        if upper <= old_upper {
+            return Ok(false);
+        }
+        self.map_growth_stats.record(upper);
+        trace::map_grown(upper);
+        Ok(true)
+    }
+
+    /// Environment information and derived metrics.
+    pub fn info(&self) -> Result<EnvInfo> {
+        let mut info = MDBX_envinfo::default();
+        mdbx_result(unsafe {
+            mdbx_env_info_ex(
+                self.env,
+                ptr::null(),
+                &mut info,
+                std::mem::size_of::<MDBX_envinfo>(),
+            )
+        })?;
+        Ok(info.into())
+    }
+
+    /// B-tree statistics of the whole environment.
+    pub fn stat(&self) -> Result<Stat> {
+        let mut stat = MDBX_stat::default();
+        mdbx_result(unsafe {
+            mdbx_env_stat_ex(
+                self.env,
+                ptr::null(),
+                &mut stat,
+                std::mem::size_of::<MDBX_stat>(),
+            )
+        })?;
+        Ok(stat.into())
+    }
+
+    /// Set a runtime option.
+    pub fn set(&self, option: EnvOption) -> Result<()> {
+        option.validate()?;
+        let value = option.encode()?;
+        mdbx_result(unsafe { mdbx_env_set_option(self.env, option.id(), value) })
+    }
+
+    /// Read a runtime option, e.g. `env.get::<opt::SyncPeriod>()`.
+    pub fn get<O: EnvOptionKind>(&self) -> Result<O::Value> {
+        self.get_raw_option(O::ID).map(O::decode)
+    }
+
+    /// Snapshot of all runtime options, for diagnostics dumps.
+    pub fn options(&self) -> Result<EnvOptions> {
+        let mut options = Vec::with_capacity(EnvOption::ALL.len());
+        for &id in EnvOption::ALL {
+            let raw = self.get_raw_option(id)?;
+            options.extend(EnvOption::decode(id, raw));
+        }
+        Ok(EnvOptions(options))
+    }
+
+    fn get_raw_option(&self, id: c_int) -> Result<u64> {
+        let mut value = 0u64;
+        mdbx_result(unsafe { mdbx_env_get_option(self.env, id, &mut value) })?;
+        Ok(value)
+    }
+
+    /// Commit latency histograms, if enabled with
+    /// [`EnvironmentBuilder::commit_latency_stats`].
+    pub fn commit_latency_stats(&self) -> Option<&CommitLatencyStats> {
+        self.commit_latency.as_ref()
+    }
+
+    /// Commit subscribers, if change capture was enabled with
+    /// [`EnvironmentBuilder::capture_changes`].
+    pub fn change_feed(&self) -> Option<&ChangeFeed> {
+        self.change_feed.as_ref()
+    }
+
+    /// Flags the environment was opened with.
+    pub fn flags(&self) -> Result<EnvFlags> {
+        let mut flags = 0;
+        mdbx_result(unsafe { mdbx_env_get_flags(self.env, &mut flags) })?;
+        Ok(EnvFlags::from_bits_retain(flags))
+    }
+
+    /// Maximum number of reader slots.
+    pub fn max_readers(&self) -> Result<u32> {
+        let mut readers = 0;
+        mdbx_result(unsafe { mdbx_env_get_maxreaders(self.env, &mut readers) })?;
+        Ok(readers)
+    }
+
+    /// Growth counters, if a policy was set with
+    /// [`EnvironmentBuilder::map_growth`].
+    pub fn map_growth_stats(&self) -> Option<&MapGrowthStats> {
+        self.map_growth.map(|_| &self.map_growth_stats)
+    }
+
+    /// Raw environment handle for use with the FFI functions.
+    #[inline]
+    pub fn as_ptr(&self) -> *mut MDBX_env {
+        self.env
+    }
+
+    pub(crate) fn set_registration(&mut self, id: u64) {
+        self.registration = Some(id);
+    }
+}
+
+/// Collects environment settings and applies them in the order the engine
+/// requires before `mdbx_env_open`.
+///
+/// ```rust,ignore
+/// let env = Environment::builder()
+///     .geometry("upper=100GiB,growth=256MiB".parse()?)
+///     .max_dbs(16)
+///     .sync_mode(SyncMode::SafeNoSync)
+///     .open("./mydb")?;
+/// ```
+#[derive(Debug, Clone)]
+pub struct EnvironmentBuilder {
+    flags: EnvFlags,
+    sync_mode: SyncMode,
+    mode: mdbx_mode_t,
+    geometry: Option<Geometry>,
+    max_dbs: Option<MDBX_dbi>,
+    max_readers: Option<u32>,
+    options: Vec<EnvOption>,
+    commit_latency_stats: bool,
+    capture_changes: bool,
+    map_growth: Option<MapGrowth>,
+    retry: RetryPolicy,
+}
+
+impl Default for EnvironmentBuilder {
+    fn default() -> Self {
+        EnvironmentBuilder {
+            flags: EnvFlags::empty(),
+            sync_mode: SyncMode::Durable,
+            mode: 0o644,
+            geometry: None,
+            max_dbs: None,
+            max_readers: None,
+            options: Vec::new(),
+            commit_latency_stats: false,
+            capture_changes: false,
+            map_growth: None,
+            retry: RetryPolicy::default(),
+        }
+    }
+}
+
+impl EnvironmentBuilder {
+    /// Environment flags.
+    pub fn flags(mut self, flags: EnvFlags) -> Self {
+        self.flags = flags;
+        self
+    }
+
+    /// Durability mode.
+    pub fn sync_mode(mut self, sync_mode: SyncMode) -> Self {
+        self.sync_mode = sync_mode;
+        self
+    }
+
+    /// File mode for newly created files (default `0o644`).
+    pub fn mode(mut self, mode: mdbx_mode_t) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Database size limits.
+    pub fn geometry(mut self, geometry: Geometry) -> Self {
+        self.geometry = Some(geometry);
+        self
+    }
+
+    /// Maximum number of named databases.
+    pub fn max_dbs(mut self, max_dbs: MDBX_dbi) -> Self {
+        self.max_dbs = Some(max_dbs);
+        self
+    }
+
+    /// Maximum number of concurrent readers.
+    pub fn max_readers(mut self, max_readers: u32) -> Self {
+        self.max_readers = Some(max_readers);
+        self
+    }
+
+    /// Runtime option applied when opening.
+    ///
+    /// `MaxDb` and `MaxReaders` are the same settings as
+    /// [`max_dbs`](Self::max_dbs) and [`max_readers`](Self::max_readers);
+    /// whichever is set last wins.
+    pub fn option(mut self, option: EnvOption) -> Self {
+        match option {
+            EnvOption::MaxDb(dbs) => {
+                self.max_dbs = Some(dbs.try_into().unwrap_or(MDBX_dbi::MAX));
+            }
+            EnvOption::MaxReaders(readers) => {
+                self.max_readers = Some(readers.try_into().unwrap_or(u32::MAX));
+            }
+            option => self.options.push(option),
+        }
+        self
+    }
+
+    /// Record the latency of every commit in per-phase histograms.
+    pub fn commit_latency_stats(mut self, enabled: bool) -> Self {
+        self.commit_latency_stats = enabled;
+        self
+    }
+
+    /// Record the tables and keys changed by each write transaction and
+    /// report them to the [`ChangeFeed`] after commit.
+    pub fn capture_changes(mut self, enabled: bool) -> Self {
+        self.capture_changes = enabled;
+        self
+    }
+
+    /// Grow the map instead of failing when [`Environment::write`] hits
+    /// `MDBX_MAP_FULL`.
+    pub fn map_growth(mut self, growth: MapGrowth) -> Self {
+        self.map_growth = Some(growth);
+        self
+    }
+
+    /// Retries of [`Environment::read`] and [`Environment::write`] on
+    /// transient errors (default: [`RetryPolicy::default`]).
+    pub fn retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Open the environment at `path`.
+    ///
+    /// The first open in the process verifies that the linked engine matches
+    /// this crate's version and fails with `MDBX_VERSION_MISMATCH` otherwise.
+    /// Fails with `AlreadyOpen` if the file is already open in the process;
+    /// use [`open_shared`](Self::open_shared) to share one environment.
+    pub fn open(&self, path: impl AsRef<Path>) -> Result<Environment> {
+        let path = path.as_ref();
+        registry::open_exclusive(path, || self.open_env(path))
+    }
+
+    fn open_env(&self, path: &Path) -> Result<Environment> {
+        check_engine_version()?;
+        let path = path_to_cstring(path)?;
+        if let Some(geometry) = &self.geometry {
+            geometry.validate()?;
+        }
+        if let Some(max_dbs) = self.max_dbs {
+            EnvOption::MaxDb(max_dbs.into()).validate()?;
+        }
+        if let Some(max_readers) = self.max_readers {
+            EnvOption::MaxReaders(max_readers.into()).validate()?;
+        }
+        for option in &self.options {
+            option.validate()?;
+        }
+        if let Some(growth) = &self.map_growth {
+            growth.validate()?;
+        }
+
+        let mut env = ptr::null_mut();
+        mdbx_result(unsafe { mdbx_env_create(&mut env) })?;
+        // Closes the handle if any of the steps below fails
+        let env = Environment {
+            env,
+            commit_latency: self.commit_latency_stats.then(CommitLatencyStats::default),
+            change_feed: self.capture_changes.then(ChangeFeed::default),
+            map_growth: self.map_growth,
+            map_growth_stats: MapGrowthStats::default(),
+            retry: self.retry,
+            registration: None,
+        };
+
+        if let Some(max_dbs) = self.max_dbs {
+            mdbx_result(unsafe { mdbx_env_set_maxdbs(env.env, max_dbs) })?;
+        }
+        if let Some(max_readers) = self.max_readers {
+            mdbx_result(unsafe { mdbx_env_set_maxreaders(env.env, max_readers) })?;
+        }
+        if let Some(geometry) = self.geometry {
+            let [lower, now, upper, growth, shrink, page_size] = geometry.to_args();
+            mdbx_result(unsafe {
+                mdbx_env_set_geometry(env.env, lower, now, upper, growth, shrink, page_size)
+            })?;
+        }
+        // Values kept in the lock file can only be set once it is open
+        let (after_open, before_open): (Vec<&EnvOption>, Vec<&EnvOption>) = self
+            .options
+            .iter()
+            .partition(|option| option.needs_open_env());
+        for option in before_open {
+            env.set(*option)?;
+        }
+
+        let flags = self.flags.with_sync_mode(self.sync_mode);
+        mdbx_result(unsafe { mdbx_env_open(env.env, path.as_ptr(), flags, self.mode) })?;
+        for option in after_open {
+            env.set(*option)?;
+        }
+        Ok(env)
+    }
+
+    /// Open the environment at `path`, or share the one already opened this
+    /// way in the process.
+    ///
+    /// The engine allows only one open of a database per process. Repeated
+    /// shared opens of the same file, found by canonical path or device and
+    /// inode, return the same `Arc` and ignore every setting but the flags;
+    /// they fail with `InvalidFlags` if the flags or sync mode differ, and
+    /// with `AlreadyOpen` if the file was opened with [`open`](Self::open).
+    ///
+    /// ```rust,ignore
+    /// let a = Environment::builder().max_dbs(4).open_shared("./mydb")?;
+    /// let b = Environment::builder().max_dbs(4).open_shared("./mydb/.")?;
+    /// assert!(Arc::ptr_eq(&a, &b));
+    /// ```
+    pub fn open_shared(&self, path: impl AsRef<Path>) -> Result<Arc<Environment>> {
+        let path = path.as_ref();
+        let flags = self.flags.with_sync_mode(self.sync_mode);
+        registry::open_shared(path, flags, || self.open_env(path))
+    }
+}
+
+impl Drop for Environment {
+    fn drop(&mut self) {
+        unsafe {
+            mdbx_env_close(self.env);
+        }
+        // Only now may the file be opened again
+        if let Some(id) = self.registration {
+            registry::closed(id);
+        }
+    }
+}
+
+#[cfg(unix)]
+pub(crate) fn path_to_cstring(path: &Path) -> Result<CString> {
+    use std::os::unix::ffi::OsStrExt;
+    CString::new(path.as_os_str().as_bytes()).map_err(|_| Error::from_code(MDBX_EINVAL))
+}
+
+#[cfg(not(unix))]
+pub(crate) fn path_to_cstring(path: &Path) -> Result<CString> {
+    let path = path.to_str().ok_or(Error::from_code(MDBX_EINVAL))?;
+    CString::new(path).map_err(|_| Error::from_code(MDBX_EINVAL))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+    use std::time::{Duration, Instant};
+
+    use super::*;
+    use crate::constants::MDBX_BUSY;
+    use crate::database::Database;
+    use crate::flags::{DbFlags, PutFlags};
+    use crate::options::opt;
+    use crate::test_util::TestEnv;
+    use crate::transaction::Txn;
+
+    #[test]
+    fn test_open_with_options() {
+        let env = TestEnv::with(
+            Environment::builder()
+                .max_dbs(2)
+                .option(EnvOption::MaxDb(6))
+                .option(EnvOption::SyncBytes(1 << 20))
+                .option(EnvOption::SyncPeriod(Duration::from_secs(2)))
+                .option(EnvOption::LooseLimit(32)),
+        );
+        assert_eq!(env.get::<opt::MaxDb>().unwrap(), 6);
+        assert_eq!(env.get::<opt::SyncBytes>().unwrap(), 1 << 20);
+        assert_eq!(
+            env.get::<opt::SyncPeriod>().unwrap(),
+            Duration::from_secs(2)
+        );
+        assert_eq!(env.get::<opt::LooseLimit>().unwrap(), 32);
+
+        let env = TestEnv::with(
+            Environment::builder()
+                .option(EnvOption::MaxReaders(1000))
+                .max_readers(20),
+        );
+        // The engine rounds up to fill the lock file's pages
+        let readers = env.get::<opt::MaxReaders>().unwrap();
+        assert!((20..1000).contains(&readers), "{}", readers);
+    }
+
+    #[test]
+    fn test_write_grows_full_map() {
+        let geometry = Geometry {
+            size_lower: Some(1 << 20),
+            size_now: Some(1 << 20),
+            size_upper: Some(1 << 20),
+            growth_step: Some(1 << 20),
+            ..Geometry::default()
+        };
+        let env = TestEnv::with(
+            Environment::builder()
+                .geometry(geometry)
+                .map_growth(MapGrowth::new(16 << 20)),
+        );
+        let db = env
+            .write(|txn| txn.create_db(None, DbFlags::empty()))
+            .unwrap();
+
+        // 2 MiB of values can't fit the initial 1 MiB map
+        let mut attempts = 0;
+        env.write(|txn| {
+            attempts += 1;
+            for i in 0..512u32 {
+                txn.put(db, &i.to_be_bytes(), &[7; 4096], PutFlags::empty())?;
+            }
+            Ok(())
+        })
+        .unwrap();
+        assert!(attempts > 1);
+        let stats = env.map_growth_stats().unwrap();
+        assert_eq!(stats.events(), attempts - 1);
+        assert!(stats.upper() > 2 << 20);
+        assert_eq!(stats.upper(), env.info().unwrap().geometry.upper);
+
+        let txn = env.begin_ro_txn().unwrap();
+        assert_eq!(txn.iter(db).unwrap().count(), 512);
+    }
+
+    #[test]
+    fn test_write_stops_at_growth_limit() {
+        let geometry = Geometry {
+            size_lower: Some(1 << 20),
+            size_now: Some(1 << 20),
+            size_upper: Some(1 << 20),
+            growth_step: Some(1 << 20),
+            ..Geometry::default()
+        };
+        let env = TestEnv::with(
+            Environment::builder()
+                .geometry(geometry)
+                .map_growth(MapGrowth::new(2 << 20)),
+        );
+        let db = env
+            .write(|txn| txn.create_db(None, DbFlags::empty()))
+            .unwrap();
+        let err = env
+            .write(|txn| {
+                for i in 0..1024u32 {
+                    txn.put(db, &i.to_be_bytes(), &[7; 4096], PutFlags::empty())?;
+                }
+                Ok(())
+            })
+            .unwrap_err();
+        assert_eq!(err, Error::Mdbx(MDBX_MAP_FULL));
+        assert_eq!(env.map_growth_stats().unwrap().events(), 1);
+    }
+
+    /// Hold the write lock on another thread until `release` is dropped,
+    /// then commit `b"held"`.
+    fn hold_write_lock<'scope>(
+        scope: &'scope thread::Scope<'scope, '_>,
+        env: &'scope Environment,
+        db: Database,
+        release: mpsc::Receiver<()>,
+    ) -> thread::ScopedJoinHandle<'scope, ()> {
+        let (locked, wait) = mpsc::channel();
+        let holder = scope.spawn(move || {
+            let txn = env.begin_rw_txn().unwrap();
+            txn.put(db, b"held", b"1", PutFlags::empty()).unwrap();
+            locked.send(()).unwrap();
+            let _ = release.recv();
+            txn.commit().unwrap();
+        });
+        wait.recv().unwrap();
+        holder
+    }
+
+    #[test]
+    fn test_try_writer_retries_busy() {
+        let policy = RetryPolicy::new(1000)
+            .backoff(Duration::from_millis(1), Duration::from_millis(5))
+            .try_writer(true);
+        let env = TestEnv::with(Environment::builder().retry_policy(policy));
+        let db = env
+            .write(|txn| txn.create_db(None, DbFlags::empty()))
+            .unwrap();
+
+        thread::scope(|scope| {
+            let (release, wait) = mpsc::channel();
+            let holder = hold_write_lock(scope, &env, db, wait);
+            scope.spawn(move || {
+                thread::sleep(Duration::from_millis(50));
+                drop(release);
+            });
+            let start = Instant::now();
+            env.write(|txn| {
+                // Only runs once the other writer has committed
+                assert!(txn.get(db, b"held")?.is_some());
+                txn.put(db, b"retried", b"1", PutFlags::empty())
+            })
+            .unwrap();
+            assert!(start.elapsed() >= Duration::from_millis(40));
+            holder.join().unwrap();
+        });
+        let txn = env.begin_ro_txn().unwrap();
+        assert!(txn.get(db, b"retried").unwrap().is_some());
+    }
+
+    #[test]
+    fn test_try_writer_gives_up() {
+        let policy = RetryPolicy::new(3)
+            .backoff(Duration::from_millis(1), Duration::from_millis(1))
+            .try_writer(true);
+        let env = TestEnv::with(Environment::builder().retry_policy(policy));
+        let db = env
+            .write(|txn| txn.create_db(None, DbFlags::empty()))
+            .unwrap();
+
+        thread::scope(|scope| {
+            let (release, wait) = mpsc::channel::<()>();
+            let holder = hold_write_lock(scope, &env, db, wait);
+            let mut runs = 0;
+            let err = env
+                .write(|txn| {
+                    runs += 1;
+                    txn.put(db, b"never", b"1", PutFlags::empty())
+                })
+                .unwrap_err();
+            assert_eq!(err, Error::Mdbx(MDBX_BUSY));
+            assert_eq!(runs, 0);
+            drop(release);
+            holder.join().unwrap();
+        });
+        let txn = env.begin_ro_txn().unwrap();
+        assert!(txn.get(db, b"held").unwrap().is_some());
+        assert!(txn.get(db, b"never").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_open_rejects_invalid_options() {
+        let dir = tempfile::tempdir().unwrap();
+        for builder in [
+            Environment::builder().option(EnvOption::MaxDb(100_000)),
+            Environment::builder().max_readers(0),
+            Environment::builder().option(EnvOption::LooseLimit(256)),
+        ] {
+            assert!(matches!(
+                builder.open(dir.path()),
+                Err(Error::InvalidOption(_))
+            ));
+        }
+    }
+}
@@ -0,0 +1,203 @@
+//! Error type for the safe API.
+
+use std::ffi::CStr;
+use std::fmt;
+use std::os::raw::{c_char, c_int};
+use std::path::PathBuf;
+
+use crate::constants::*;
+use crate::{mdbx_liberr2str, mdbx_strerror_r};
+
+/// Result type returned by the safe API.
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Error returned by the safe API.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// Error code returned by the engine (`MDBX_*` or a system `errno`).
+    Mdbx(c_int),
+    /// Flag combination the engine would reject.
+    InvalidFlags(&'static str),
+    /// Option value outside the range the engine accepts.
+    InvalidOption(&'static str),
+    /// Key or value that a table codec could not encode or decode.
+    Codec(String),
+    /// Entry `index` of a bulk load was not ordered after its predecessor.
+    OutOfOrder { index: u64, key: Vec<u8> },
+    /// Database file that is already open in this process.
+    AlreadyOpen(PathBuf),
+}
+
+impl Error {
+    /// Wrap a raw return code.
+    #[inline]
+    pub fn from_code(code: c_int) -> Self {
+        Error::Mdbx(code)
+    }
+
+    /// The raw `MDBX_*` code for this error.
+    #[inline]
+    pub fn code(&self) -> c_int {
+        match self {
+            Error::Mdbx(code) => *code,
+            Error::InvalidFlags(_) | Error::InvalidOption(_) | Error::Codec(_) => MDBX_EINVAL,
+            Error::OutOfOrder { .. } => MDBX_EKEYMISMATCH,
+            Error::AlreadyOpen(_) => MDBX_BUSY,
+        }
+    }
+
+    /// Message for this error as reported by the engine via `mdbx_strerror_r`.
+    pub fn engine_message(&self) -> String {
+        strerror(self.code())
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Mdbx(code) => match liberr_message(*code) {
+                Some(message) => f.write_str(message),
+                None => f.write_str(&strerror(*code)),
+            },
+            Error::InvalidFlags(reason) => write!(f, "invalid flags: {}", reason),
+            Error::InvalidOption(reason) => write!(f, "invalid option: {}", reason),
+            Error::Codec(reason) => write!(f, "codec error: {}", reason),
+            Error::OutOfOrder { index, key } => {
+                write!(f, "entry {} is out of order, key ", index)?;
+                key.iter().try_for_each(|byte| write!(f, "{:02x}", byte))
+            }
+            Error::AlreadyOpen(path) => write!(
+                f,
+                "environment at {} is already open in this process",
+                path.display()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Map an I/O error to its `errno`, like the engine reports them.
+pub(crate) fn io_error(err: std::io::Error) -> Error {
+    Error::from_code(err.raw_os_error().unwrap_or(MDBX_EIO))
+}
+
+/// Message for an MDBX-specific error code via `mdbx_liberr2str`.
+///
+/// Returns `None` for system `errno` values and unassigned codes.
+pub fn liberr_message(code: c_int) -> Option<&'static str> {
+    // SAFETY: the engine returns null or a static, NUL-terminated string.
+    let message = unsafe { mdbx_liberr2str(code) };
+    if message.is_null() {
+        return None;
+    }
+    unsafe { CStr::from_ptr(message) }.to_str().ok()
+}
+
+/// Thread-safe message for any return code via `mdbx_strerror_r`.
+pub fn strerror(code: c_int) -> String {
+    let mut buf = [0 as c_char; 1024];
+    unsafe {
+        let message = mdbx_strerror_r(code, buf.as_mut_ptr(), buf.len());
+        if message.is_null() {
+            return format!("unknown error {}", code);
+        }
+        CStr::from_ptr(message).to_string_lossy().into_owned()
+    }
+}
+
+/// Convert a return code into a `Result`.
+#[inline]
+pub(crate) fn mdbx_result(rc: c_int) -> Result<()> {
+    if rc == MDBX_SUCCESS {
+        Ok(())
+    } else {
+        Err(Error::from_code(rc))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    #[test]
+    fn test_mdbx_result() {
+        assert_eq!(mdbx_result(MDBX_SUCCESS), Ok(()));
+        assert_eq!(mdbx_result(MDBX_NOTFOUND), Err(Error::Mdbx(MDBX_NOTFOUND)));
+        assert_eq!(Error::from_code(MDBX_MAP_FULL).code(), MDBX_MAP_FULL);
+    }
+
+    #[test]
+    fn test_error_messages() {
+        let mut messages = HashSet::new();
+        for code in MDBX_FIRST_LMDB_ERRCODE..=MDBX_LAST_ADDED_ERRCODE {
+            let message = strerror(code);
+            assert!(!message.is_empty(), "empty message for {}", code);
+            assert!(messages.insert(message), "duplicate message for {}", code);
+            let expected = liberr_message(code).map_or_else(|| strerror(code), str::to_owned);
+            assert_eq!(Error::from_code(code).to_string(), expected);
+        }
+
+        let mut messages = HashSet::new();
+        for code in [
+            MDBX_KEYEXIST,
+            MDBX_NOTFOUND,
+            MDBX_PAGE_NOTFOUND,
+            MDBX_CORRUPTED,
+            MDBX_PANIC,
+            MDBX_VERSION_MISMATCH,
+            MDBX_INVALID,
+            MDBX_MAP_FULL,
+            MDBX_DBS_FULL,
+            MDBX_READERS_FULL,
+            MDBX_TXN_FULL,
+            MDBX_CURSOR_FULL,
+            MDBX_PAGE_FULL,
+            MDBX_UNABLE_EXTEND_MAPSIZE,
+            MDBX_INCOMPATIBLE,
+            MDBX_BAD_RSLOT,
+            MDBX_BAD_TXN,
+            MDBX_BAD_VALSIZE,
+            MDBX_BAD_DBI,
+            MDBX_PROBLEM,
+            MDBX_BUSY,
+            MDBX_EMULTIVAL,
+            MDBX_EBADSIGN,
+            MDBX_WANNA_RECOVERY,
+            MDBX_EKEYMISMATCH,
+            MDBX_TOO_LARGE,
+            MDBX_THREAD_MISMATCH,
+            MDBX_TXN_OVERLAPPING,
+            MDBX_BACKLOG_DEPLETED,
+            MDBX_DUPLICATED_CLK,
+            MDBX_DANGLING_DBI,
+            MDBX_OUSTED,
+            MDBX_MVCC_RETARDED,
+        ] {
+            let message = liberr_message(code).unwrap_or_else(|| panic!("no message for {}", code));
+            assert!(!message.is_empty(), "empty message for {}", code);
+            assert!(messages.insert(message), "duplicate message for {}", code);
+            assert_eq!(strerror(code), message);
+        }
+    }
+
+    #[test]
+    fn test_out_of_order_message() {
+        let err = Error::OutOfOrder {
+            index: 3,
+            key: vec![0x0a, 0xff],
+        };
+        assert_eq!(err.to_string(), "entry 3 is out of order, key 0aff");
+        assert_eq!(err.code(), MDBX_EKEYMISMATCH);
+    }
+
+    #[test]
+    fn test_system_error_message() {
+        let message = Error::from_code(MDBX_EINVAL).to_string();
+        assert!(!message.is_empty());
+        assert_eq!(liberr_message(MDBX_EINVAL), None);
+    }
+}
@@ -0,0 +1,288 @@
+//! Strongly typed flag sets for the safe API.
+//!
+//! The raw `MDBX_*` constants are `c_int` while the FFI functions take
+//! `c_uint`, and nothing stops environment flags from being passed as put
+//! flags. Each set here has the engine's underlying integer type and only the
+//! flags valid for its parameter.
+
+use std::os::raw::{c_int, c_uint};
+
+use bitflags::bitflags;
+
+use crate::constants::*;
+use crate::error::{Error, Result};
+use crate::{MDBX_env_flags_t, MDBX_txn_flags_t};
+
+bitflags! {
+    /// Environment flags for `mdbx_env_open`.
+    ///
+    /// Durability is configured separately with [`SyncMode`].
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+    pub struct EnvFlags: MDBX_env_flags_t {
+        const VALIDATION = MDBX_VALIDATION as MDBX_env_flags_t;
+        const NOSUBDIR = MDBX_NOSUBDIR as MDBX_env_flags_t;
+        const RDONLY = MDBX_RDONLY as MDBX_env_flags_t;
+        const EXCLUSIVE = MDBX_EXCLUSIVE as MDBX_env_flags_t;
+        const ACCEDE = MDBX_ACCEDE as MDBX_env_flags_t;
+        const WRITEMAP = MDBX_WRITEMAP as MDBX_env_flags_t;
+        const NOSTICKYTHREADS = MDBX_NOSTICKYTHREADS as MDBX_env_flags_t;
+        const NORDAHEAD = MDBX_NORDAHEAD as MDBX_env_flags_t;
+        const NOMEMINIT = MDBX_NOMEMINIT as MDBX_env_flags_t;
+        const COALESCE = MDBX_COALESCE as MDBX_env_flags_t;
+        const LIFORECLAIM = MDBX_LIFORECLAIM as MDBX_env_flags_t;
+        const PAGEPERTURB = MDBX_PAGEPERTURB as MDBX_env_flags_t;
+    }
+}
+
+impl EnvFlags {
+    /// Raw flags for `mdbx_env_open` combined with the given sync mode.
+    #[inline]
+    pub fn with_sync_mode(self, mode: SyncMode) -> MDBX_env_flags_t {
+        self.bits() | mode.bits()
+    }
+}
+
+/// Durability mode of an environment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum SyncMode {
+    /// Full durability (`MDBX_SYNC_DURABLE`).
+    #[default]
+    Durable,
+    /// Don't sync meta pages on commit (`MDBX_NOMETASYNC`).
+    NoMetaSync,
+    /// Don't sync on commit, keeping the database consistent (`MDBX_SAFE_NOSYNC`).
+    SafeNoSync,
+    /// Don't sync at all; a crash may corrupt the database (`MDBX_UTTERLY_NOSYNC`).
+    UtterlyNoSync,
+}
+
+impl SyncMode {
+    /// Raw flag bits for `mdbx_env_open`.
+    #[inline]
+    pub fn bits(self) -> MDBX_env_flags_t {
+        let bits = match self {
+            SyncMode::Durable => MDBX_SYNC_DURABLE,
+            SyncMode::NoMetaSync => MDBX_NOMETASYNC,
+            SyncMode::SafeNoSync => MDBX_SAFE_NOSYNC,
+            SyncMode::UtterlyNoSync => MDBX_UTTERLY_NOSYNC,
+        };
+        bits as MDBX_env_flags_t
+    }
+
+    /// Extract the sync mode from raw environment flags.
+    pub fn from_bits(bits: MDBX_env_flags_t) -> Self {
+        let bits = bits as c_int & MDBX_UTTERLY_NOSYNC;
+        match bits {
+            MDBX_UTTERLY_NOSYNC => SyncMode::UtterlyNoSync,
+            MDBX_SAFE_NOSYNC => SyncMode::SafeNoSync,
+            MDBX_NOMETASYNC => SyncMode::NoMetaSync,
+            _ => SyncMode::Durable,
+        }
+    }
+}
+
+bitflags! {
+    /// Transaction flags for `mdbx_txn_begin`.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+    pub struct TxnFlags: MDBX_txn_flags_t {
+        const RDONLY = MDBX_TXN_RDONLY as MDBX_txn_flags_t;
+        const RDONLY_PREPARE = MDBX_TXN_RDONLY_PREPARE as MDBX_txn_flags_t;
+        const TRY = MDBX_TXN_TRY as MDBX_txn_flags_t;
+        const NOMETASYNC = MDBX_TXN_NOMETASYNC as MDBX_txn_flags_t;
+        const NOSYNC = MDBX_TXN_NOSYNC as MDBX_txn_flags_t;
+    }
+}
+
+impl TxnFlags {
+    /// Reject combinations the engine would refuse.
+    pub fn validate(self) -> Result<()> {
+        let write_only = TxnFlags::TRY | TxnFlags::NOMETASYNC | TxnFlags::NOSYNC;
+        if self.contains(TxnFlags::RDONLY) && self.intersects(write_only) {
+            return Err(Error::InvalidFlags(
+                "MDBX_TXN_TRY/NOSYNC/NOMETASYNC apply only to write transactions",
+            ));
+        }
+        Ok(())
+    }
+}
+
+bitflags! {
+    /// Database flags for `mdbx_dbi_open`.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+    pub struct DbFlags: c_uint {
+        const REVERSEKEY = MDBX_REVERSEKEY as c_uint;
+        const DUPSORT = MDBX_DUPSORT as c_uint;
+        const INTEGERKEY = MDBX_INTEGERKEY as c_uint;
+        const DUPFIXED = MDBX_DUPFIXED as c_uint;
+        const INTEGERDUP = MDBX_INTEGERDUP as c_uint;
+        const REVERSEDUP = MDBX_REVERSEDUP as c_uint;
+        const CREATE = MDBX_CREATE as c_uint;
+        const ACCEDE = MDBX_DB_ACCEDE as c_uint;
+    }
+}
+
+impl DbFlags {
+    /// Reject combinations the engine would refuse.
+    pub fn validate(self) -> Result<()> {
+        let dup_only = DbFlags::DUPFIXED | DbFlags::INTEGERDUP | DbFlags::REVERSEDUP;
+        if self.intersects(dup_only) && !self.contains(DbFlags::DUPSORT) {
+            return Err(Error::InvalidFlags(
+                "MDBX_DUPFIXED/INTEGERDUP/REVERSEDUP require MDBX_DUPSORT",
+            ));
+        }
+        if self.contains(DbFlags::INTEGERDUP) && !self.contains(DbFlags::DUPFIXED) {
+            return Err(Error::InvalidFlags(
+                "MDBX_INTEGERDUP requires MDBX_DUPFIXED",
+            ));
+        }
+        if self.contains(DbFlags::INTEGERKEY | DbFlags::REVERSEKEY) {
+            return Err(Error::InvalidFlags(
+                "MDBX_INTEGERKEY and MDBX_REVERSEKEY are mutually exclusive",
+            ));
+        }
+        Ok(())
+    }
+}
+
+bitflags! {
+    /// Write flags for `mdbx_put` and `mdbx_cursor_put`.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+    pub struct PutFlags: c_uint {
+        const NOOVERWRITE = MDBX_NOOVERWRITE as c_uint;
+        const NODUPDATA = MDBX_NODUPDATA as c_uint;
+        const CURRENT = MDBX_CURRENT as c_uint;
+        const ALLDUPS = MDBX_ALLDUPS as c_uint;
+        const RESERVE = MDBX_RESERVE as c_uint;
+        const APPEND = MDBX_APPEND as c_uint;
+        const APPENDDUP = MDBX_APPENDDUP as c_uint;
+        const MULTIPLE = MDBX_MULTIPLE as c_uint;
+    }
+}
+
+impl PutFlags {
+    /// Reject combinations the engine would refuse for a database with `db_flags`.
+    pub fn validate_for(self, db_flags: DbFlags) -> Result<()> {
+        let dup_only = PutFlags::NODUPDATA | PutFlags::APPENDDUP | PutFlags::MULTIPLE;
+        if self.intersects(dup_only) && !db_flags.contains(DbFlags::DUPSORT) {
+            return Err(Error::InvalidFlags(
+                "MDBX_NODUPDATA/APPENDDUP/MULTIPLE require an MDBX_DUPSORT database",
+            ));
+        }
+        if self.contains(PutFlags::MULTIPLE) && !db_flags.contains(DbFlags::DUPFIXED) {
+            return Err(Error::InvalidFlags(
+                "MDBX_MULTIPLE requires an MDBX_DUPFIXED database",
+            ));
+        }
+        if self.contains(PutFlags::RESERVE) && db_flags.contains(DbFlags::DUPSORT) {
+            return Err(Error::InvalidFlags(
+                "MDBX_RESERVE is not supported for MDBX_DUPSORT databases",
+            ));
+        }
+        Ok(())
+    }
+}
+
+bitflags! {
+    /// Flags for `mdbx_env_copy`.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+    pub struct CopyFlags: c_uint {
+        const COMPACT = MDBX_CP_COMPACT as c_uint;
+        const FORCE_DYNAMIC_SIZE = MDBX_CP_FORCE_DYNAMIC_SIZE as c_uint;
+        const DONT_FLUSH = MDBX_CP_DONT_FLUSH as c_uint;
+        const THROTTLE_MVCC = MDBX_CP_THROTTLE_MVCC as c_uint;
+        const DISPOSE_TXN = MDBX_CP_DISPOSE_TXN as c_uint;
+        const RENEW_TXN = MDBX_CP_RENEW_TXN as c_uint;
+        const OVERWRITE = MDBX_CP_OVERWRITE as c_uint;
+    }
+}
+
+bitflags! {
+    /// Flags for `mdbx_env_warmup`.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+    pub struct WarmupFlags: c_uint {
+        const FORCE = MDBX_WARMUP_FORCE as c_uint;
+        const OOMSAFE = MDBX_WARMUP_OOMSAFE as c_uint;
+        const LOCK = MDBX_WARMUP_LOCK as c_uint;
+        const TOUCHLIMIT = MDBX_WARMUP_TOUCHLIMIT as c_uint;
+        const RELEASE = MDBX_WARMUP_RELEASE as c_uint;
+    }
+}
+
+bitflags! {
+    /// Debug flags for `mdbx_setup_debug`.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+    pub struct DebugFlags: c_int {
+        const ASSERT = MDBX_DBG_ASSERT;
+        const AUDIT = MDBX_DBG_AUDIT;
+        const JITTER = MDBX_DBG_JITTER;
+        const DUMP = MDBX_DBG_DUMP;
+        const LEGACY_MULTIOPEN = MDBX_DBG_LEGACY_MULTIOPEN;
+        const LEGACY_OVERLAP = MDBX_DBG_LEGACY_OVERLAP;
+        const DONT_UPGRADE = MDBX_DBG_DONT_UPGRADE;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flag_bits() {
+        assert_eq!(EnvFlags::NOSUBDIR.bits(), 0x4000);
+        assert_eq!(EnvFlags::NOSTICKYTHREADS.bits(), MDBX_NOTLS as u32);
+        assert_eq!(TxnFlags::RDONLY.bits(), MDBX_RDONLY as u32);
+        assert_eq!(DbFlags::DUPSORT.bits(), 0x04);
+        assert_eq!(PutFlags::APPENDDUP.bits(), 0x40000);
+        assert_eq!(CopyFlags::COMPACT.bits(), 1);
+        assert_eq!(WarmupFlags::RELEASE.bits(), 16);
+        assert_eq!(DebugFlags::DONT_UPGRADE.bits(), 64);
+    }
+
+    #[test]
+    fn test_sync_mode_bits() {
+        for mode in [
+            SyncMode::Durable,
+            SyncMode::NoMetaSync,
+            SyncMode::SafeNoSync,
+            SyncMode::UtterlyNoSync,
+        ] {
+            let bits = EnvFlags::NOSUBDIR.with_sync_mode(mode);
+            assert_eq!(SyncMode::from_bits(bits), mode);
+        }
+        assert_eq!(SyncMode::UtterlyNoSync.bits(), MDBX_UTTERLY_NOSYNC as u32);
+    }
+
+    #[test]
+    fn test_db_flags_validation() {
+        assert!(DbFlags::empty().validate().is_ok());
+        assert!((DbFlags::DUPSORT | DbFlags::INTEGERDUP).validate().is_err());
+        assert!((DbFlags::DUPSORT | DbFlags::DUPFIXED | DbFlags::INTEGERDUP)
+            .validate()
+            .is_ok());
+        assert!(DbFlags::INTEGERDUP.validate().is_err());
+        assert!(DbFlags::DUPFIXED.validate().is_err());
+        assert!((DbFlags::INTEGERKEY | DbFlags::REVERSEKEY)
+            .validate()
+            .is_err());
+    }
+
+    #[test]
+    fn test_put_flags_validation() {
+        let dupsort = DbFlags::DUPSORT;
+        assert!(PutFlags::NOOVERWRITE.validate_for(DbFlags::empty()).is_ok());
+        assert!(PutFlags::APPENDDUP.validate_for(dupsort).is_ok());
+        assert!(PutFlags::APPENDDUP.validate_for(DbFlags::empty()).is_err());
+        assert!(PutFlags::MULTIPLE.validate_for(dupsort).is_err());
+        assert!(PutFlags::MULTIPLE
+            .validate_for(dupsort | DbFlags::DUPFIXED)
+            .is_ok());
+        assert!(PutFlags::RESERVE.validate_for(dupsort).is_err());
+    }
+
+    #[test]
+    fn test_txn_flags_validation() {
+        assert!(TxnFlags::empty().validate().is_ok());
+        assert!(TxnFlags::TRY.validate().is_ok());
+        assert!((TxnFlags::RDONLY | TxnFlags::TRY).validate().is_err());
+    }
+}
@@ -0,0 +1,233 @@
+//! Database size limits for `mdbx_env_set_geometry`.
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::constants::{MDBX_MAX_PAGESIZE, MDBX_MIN_PAGESIZE};
+use crate::error::{Error, Result};
+
+/// Database geometry.
+///
+/// `None` keeps the engine's current or default value, which is passed as
+/// `-1` to `mdbx_env_set_geometry`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Geometry {
+    /// Minimum database size in bytes.
+    pub size_lower: Option<usize>,
+    /// Current/initial database size in bytes.
+    pub size_now: Option<usize>,
+    /// Maximum database size in bytes.
+    pub size_upper: Option<usize>,
+    /// Increment when growing the database.
+    pub growth_step: Option<usize>,
+    /// Unused space above which the database is shrunk.
+    pub shrink_threshold: Option<usize>,
+    /// Page size in bytes (power of two in 256..=65536).
+    pub page_size: Option<usize>,
+}
+
+impl Geometry {
+    /// Geometry with only the maximum size set.
+    pub fn with_upper(size_upper: usize) -> Self {
+        Geometry {
+            size_upper: Some(size_upper),
+            ..Default::default()
+        }
+    }
+
+    /// Check the values are consistent before handing them to the engine.
+    pub fn validate(&self) -> Result<()> {
+        let fields = [
+            self.size_lower,
+            self.size_now,
+            self.size_upper,
+            self.growth_step,
+            self.shrink_threshold,
+            self.page_size,
+        ];
+        if fields
+            .iter()
+            .flatten()
+            .any(|&size| size > isize::MAX as usize)
+        {
+            return Err(Error::InvalidOption("geometry size exceeds isize::MAX"));
+        }
+        if let Some(page_size) = self.page_size {
+            let range = MDBX_MIN_PAGESIZE as usize..=MDBX_MAX_PAGESIZE as usize;
+            if !page_size.is_power_of_two() || !range.contains(&page_size) {
+                return Err(Error::InvalidOption(
+                    "page size must be a power of two within 256..=65536",
+                ));
+            }
+        }
+        let ordered = [self.size_lower, self.size_now, self.size_upper];
+        let mut ordered = ordered.iter().flatten();
+        if let Some(mut prev) = ordered.next() {
+            for size in ordered {
+                if size < prev {
+                    return Err(Error::InvalidOption(
+                        "geometry requires size_lower <= size_now <= size_upper",
+                    ));
+                }
+                prev = size;
+            }
+        }
+        Ok(())
+    }
+
+    /// Arguments for `mdbx_env_set_geometry`, in parameter order.
+    pub(crate) fn to_args(self) -> [isize; 6] {
+        [
+            self.size_lower,
+            self.size_now,
+            self.size_upper,
+            self.growth_step,
+            self.shrink_threshold,
+            self.page_size,
+        ]
+        .map(|size| size.map_or(-1, |size| size as isize))
+    }
+}
+
+/// Parses comma-separated `key=size` pairs, e.g.
+/// `"upper=100GiB,growth=256MiB,pagesize=4KiB"`.
+///
+/// Keys are `lower`, `now`, `upper`, `growth`, `shrink` and `pagesize`.
+impl FromStr for Geometry {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut geometry = Geometry::default();
+        for pair in s.split(',').map(str::trim).filter(|pair| !pair.is_empty()) {
+            let (key, value) = pair
+                .split_once('=')
+                .ok_or(Error::InvalidOption("geometry expects key=size pairs"))?;
+            let size = Some(parse_size(value)?);
+            match key.trim() {
+                "lower" => geometry.size_lower = size,
+                "now" => geometry.size_now = size,
+                "upper" => geometry.size_upper = size,
+                "growth" => geometry.growth_step = size,
+                "shrink" => geometry.shrink_threshold = size,
+                "pagesize" => geometry.page_size = size,
+                _ => return Err(Error::InvalidOption("unknown geometry key")),
+            }
+        }
+        geometry.validate()?;
+        Ok(geometry)
+    }
+}
+
+impl fmt::Display for Geometry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let fields = [
+            ("lower", self.size_lower),
+            ("now", self.size_now),
+            ("upper", self.size_upper),
+            ("growth", self.growth_step),
+            ("shrink", self.shrink_threshold),
+            ("pagesize", self.page_size),
+        ];
+        let mut first = true;
+        for (key, size) in fields {
+            if let Some(size) = size {
+                if !first {
+                    f.write_str(",")?;
+                }
+                write!(f, "{}={}", key, size)?;
+                first = false;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parse a human-readable size such as `"4096"`, `"16MiB"`, `"100GiB"` or `"1.5TB"`.
+///
+/// Binary (`KiB`, `MiB`, `GiB`, `TiB`) and decimal (`KB`, `MB`, `GB`, `TB`)
+/// suffixes are accepted; a bare `K`/`M`/`G`/`T` is treated as binary.
+pub fn parse_size(s: &str) -> Result<usize> {
+    const INVALID: Error = Error::InvalidOption("invalid size");
+
+    let s = s.trim();
+    let split = s
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(s.len());
+    let (number, suffix) = s.split_at(split);
+    let multiplier: u64 = match suffix.trim().to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "k" | "kib" => 1 << 10,
+        "m" | "mib" => 1 << 20,
+        "g" | "gib" => 1 << 30,
+        "t" | "tib" => 1 << 40,
+        "kb" => 1_000,
+        "mb" => 1_000_000,
+        "gb" => 1_000_000_000,
+        "tb" => 1_000_000_000_000,
+        _ => return Err(INVALID),
+    };
+
+    let size = if number.contains('.') {
+        let number: f64 = number.parse().map_err(|_| INVALID)?;
+        (number * multiplier as f64).round()
+    } else {
+        let number: u64 = number.parse().map_err(|_| INVALID)?;
+        number.checked_mul(multiplier).ok_or(INVALID)? as f64
+    };
+    if size > isize::MAX as f64 {
+        return Err(INVALID);
+    }
+    Ok(size as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("4096"), Ok(4096));
+        assert_eq!(parse_size("4KiB"), Ok(4096));
+        assert_eq!(parse_size("16 MiB"), Ok(16 << 20));
+        assert_eq!(parse_size("100GiB"), Ok(100 << 30));
+        assert_eq!(parse_size("1.5GiB"), Ok(3 << 29));
+        assert_eq!(parse_size("2GB"), Ok(2_000_000_000));
+        assert_eq!(parse_size("1T"), Ok(1 << 40));
+        assert!(parse_size("").is_err());
+        assert!(parse_size("GiB").is_err());
+        assert!(parse_size("10XB").is_err());
+        assert!(parse_size("99999999999TiB").is_err());
+    }
+
+    #[test]
+    fn test_geometry_from_str() {
+        let geometry: Geometry = "upper=100GiB, growth=256MiB, pagesize=4KiB"
+            .parse()
+            .unwrap();
+        assert_eq!(geometry.size_upper, Some(100 << 30));
+        assert_eq!(geometry.growth_step, Some(256 << 20));
+        assert_eq!(geometry.page_size, Some(4096));
+        assert_eq!(geometry.size_lower, None);
+        assert_eq!(geometry.to_args(), [-1, -1, 100 << 30, 256 << 20, -1, 4096]);
+        assert_eq!(geometry.to_string().parse::<Geometry>(), Ok(geometry));
+        assert!("upper".parse::<Geometry>().is_err());
+        assert!("size=1GiB".parse::<Geometry>().is_err());
+    }
+
+    #[test]
+    fn test_geometry_validate() {
+        assert!(Geometry::default().validate().is_ok());
+        assert!(Geometry::with_upper(1 << 30).validate().is_ok());
+        let geometry = Geometry {
+            size_lower: Some(2 << 30),
+            size_upper: Some(1 << 30),
+            ..Default::default()
+        };
+        assert!(geometry.validate().is_err());
+        let geometry = Geometry {
+            page_size: Some(3000),
+            ..Default::default()
+        };
+        assert!(geometry.validate().is_err());
+    }
+}
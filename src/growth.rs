@@ -0,0 +1,118 @@
+//! Growing the map when a write hits `MDBX_MAP_FULL`.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::error::{Error, Result};
+
+/// Policy for raising the upper size bound of an open environment.
+///
+/// ```rust,ignore
+/// let env = Environment::builder()
+///     .geometry(Geometry::with_upper(1 << 30))
+///     .map_growth(MapGrowth::new(64 << 30).step(1 << 30))
+///     .open("./mydb")?;
+/// env.write(|txn| txn.put(db, b"key", b"value", PutFlags::empty()))?;
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MapGrowth {
+    max_size: usize,
+    step: Option<usize>,
+}
+
+impl MapGrowth {
+    /// Grow up to `max_size` bytes, doubling the upper bound each time.
+    pub fn new(max_size: usize) -> Self {
+        MapGrowth {
+            max_size,
+            step: None,
+        }
+    }
+
+    /// Raise the upper bound by a fixed number of bytes instead of doubling.
+    pub fn step(mut self, bytes: usize) -> Self {
+        self.step = Some(bytes);
+        self
+    }
+
+    /// Largest upper bound this policy will set.
+    #[inline]
+    pub fn max_size(&self) -> usize {
+        self.max_size
+    }
+
+    pub(crate) fn validate(&self) -> Result<()> {
+        if self.max_size > isize::MAX as usize {
+            return Err(Error::InvalidOption("map growth limit exceeds isize::MAX"));
+        }
+        if self.step == Some(0) {
+            return Err(Error::InvalidOption("map growth step must not be zero"));
+        }
+        Ok(())
+    }
+
+    /// The upper bound following `upper`, or `None` at the limit.
+    pub(crate) fn next_upper(&self, upper: u64) -> Option<u64> {
+        let max_size = self.max_size as u64;
+        if upper >= max_size {
+            return None;
+        }
+        let step = self.step.map_or(upper, |step| step as u64).max(1);
+        Some(upper.saturating_add(step).min(max_size))
+    }
+}
+
+/// Counters of the growth events of an environment.
+#[derive(Debug, Default)]
+pub struct MapGrowthStats {
+    events: AtomicU64,
+    upper: AtomicU64,
+}
+
+impl MapGrowthStats {
+    pub(crate) fn record(&self, upper: u64) {
+        self.upper.store(upper, Ordering::Relaxed);
+        self.events.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Number of times the map was grown.
+    pub fn events(&self) -> u64 {
+        self.events.load(Ordering::Relaxed)
+    }
+
+    /// Upper bound set by the last growth, or 0 if it never grew.
+    pub fn upper(&self) -> u64 {
+        self.upper.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_next_upper() {
+        let doubling = MapGrowth::new(10 << 20);
+        assert_eq!(doubling.next_upper(4 << 20), Some(8 << 20));
+        assert_eq!(doubling.next_upper(8 << 20), Some(10 << 20));
+        assert_eq!(doubling.next_upper(10 << 20), None);
+
+        let stepped = MapGrowth::new(10 << 20).step(3 << 20);
+        assert_eq!(stepped.next_upper(4 << 20), Some(7 << 20));
+        assert_eq!(stepped.next_upper(12 << 20), None);
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(MapGrowth::new(1 << 30).validate().is_ok());
+        assert!(MapGrowth::new(1 << 30).step(0).validate().is_err());
+    }
+
+    #[test]
+    fn test_stats() {
+        let stats = MapGrowthStats::default();
+        stats.record(2 << 20);
+        stats.record(4 << 20);
+        assert_eq!(stats.events(), 2);
+        assert_eq!(stats.upper(), 4 << 20);
+    }
+}
@@ -0,0 +1,251 @@
+//! Typed environment and database statistics.
+
+use std::time::Duration;
+
+use crate::options::duration_from_16dot16;
+use crate::*;
+
+/// B-tree statistics of a database or the whole environment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Stat {
+    /// Page size in bytes.
+    pub page_size: u32,
+    /// Height of the B-tree.
+    pub depth: u32,
+    pub branch_pages: u64,
+    pub leaf_pages: u64,
+    pub overflow_pages: u64,
+    /// Number of key/value pairs.
+    pub entries: u64,
+    /// Id of the transaction that last modified the database.
+    pub mod_txnid: u64,
+}
+
+impl Stat {
+    /// Total number of pages used.
+    pub fn total_pages(&self) -> u64 {
+        self.branch_pages + self.leaf_pages + self.overflow_pages
+    }
+
+    /// Total bytes used by all pages.
+    pub fn total_bytes(&self) -> u64 {
+        self.total_pages() * self.page_size as u64
+    }
+}
+
+impl From<MDBX_stat> for Stat {
+    fn from(stat: MDBX_stat) -> Self {
+        Stat {
+            page_size: stat.ms_psize,
+            depth: stat.ms_depth,
+            branch_pages: stat.ms_branch_pages,
+            leaf_pages: stat.ms_leaf_pages,
+            overflow_pages: stat.ms_overflow_pages,
+            entries: stat.ms_entries,
+            mod_txnid: stat.ms_mod_txnid,
+        }
+    }
+}
+
+/// Space usage of a transaction, from `mdbx_txn_info`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TxnInfo {
+    /// Transaction id.
+    pub id: u64,
+    /// How many transactions a reader lags behind the latest snapshot.
+    pub reader_lag: u64,
+    /// Bytes used by the snapshot seen by this transaction.
+    pub space_used: u64,
+    /// Current size of the database file.
+    pub space_limit_soft: u64,
+    /// Upper limit for the database file size.
+    pub space_limit_hard: u64,
+    /// Bytes freed by a write transaction, or retained for a reader.
+    pub space_retired: u64,
+    /// Bytes still available in pages allocated for a write transaction.
+    pub space_leftover: u64,
+    /// Bytes in pages dirtied by a write transaction.
+    pub space_dirty: u64,
+}
+
+impl From<MDBX_txn_info> for TxnInfo {
+    fn from(info: MDBX_txn_info) -> Self {
+        TxnInfo {
+            id: info.txn_id,
+            reader_lag: info.txn_reader_lag,
+            space_used: info.txn_space_used,
+            space_limit_soft: info.txn_space_limit_soft,
+            space_limit_hard: info.txn_space_limit_hard,
+            space_retired: info.txn_space_retired,
+            space_leftover: info.txn_space_leftover,
+            space_dirty: info.txn_space_dirty,
+        }
+    }
+}
+
+/// Size limits of the database file in bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct GeometryInfo {
+    pub lower: u64,
+    pub upper: u64,
+    pub current: u64,
+    pub shrink: u64,
+    pub grow: u64,
+}
+
+/// Page operation counters since the environment was opened.
+pub type PageOpStat = MDBX_envinfo_pgop_stat;
+
+/// Environment information from `mdbx_env_info_ex`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct EnvInfo {
+    pub geometry: GeometryInfo,
+    /// Size of the memory map in bytes.
+    pub map_size: u64,
+    /// Size of the database file in bytes.
+    pub file_size: u64,
+    /// Space allocated for the database file in bytes.
+    pub file_allocated: u64,
+    /// Number of the last used page.
+    pub last_pgno: u64,
+    /// Id of the most recent committed transaction.
+    pub recent_txnid: u64,
+    /// Id of the oldest snapshot still used by a reader.
+    pub latter_reader_txnid: u64,
+    /// Id of the oldest snapshot used by a reader of this process.
+    pub self_latter_reader_txnid: u64,
+    /// Transaction ids of the three meta pages.
+    pub meta_txnid: [u64; 3],
+    /// Signatures of the three meta pages.
+    pub meta_sign: [u64; 3],
+    pub max_readers: u32,
+    pub num_readers: u32,
+    /// Database page size in bytes.
+    pub page_size: u32,
+    /// System page size in bytes.
+    pub sys_page_size: u32,
+    /// Bytes written since the last sync.
+    pub unsync_volume: u64,
+    /// `sync_bytes` threshold.
+    pub autosync_threshold: u64,
+    /// Time since the last sync.
+    pub since_sync: Duration,
+    /// `sync_period` threshold.
+    pub autosync_period: Duration,
+    /// Time since the last check for dead readers.
+    pub since_reader_check: Duration,
+    /// Mode flags of the environment.
+    pub mode: u32,
+    pub pgop_stat: PageOpStat,
+}
+
+impl EnvInfo {
+    /// Bytes used by allocated pages.
+    pub fn used_bytes(&self) -> u64 {
+        (self.last_pgno + 1) * self.page_size as u64
+    }
+
+    /// Fraction of the current database size in use, in `0.0..=1.0`.
+    pub fn fill_ratio(&self) -> f64 {
+        if self.geometry.current == 0 {
+            return 0.0;
+        }
+        (self.used_bytes() as f64 / self.geometry.current as f64).min(1.0)
+    }
+
+    /// Bytes the database can still grow by before hitting `MDBX_MAP_FULL`.
+    ///
+    /// Ignores pages free in the GC, so the real headroom is larger.
+    pub fn free_space_estimate(&self) -> u64 {
+        self.geometry.upper.saturating_sub(self.used_bytes())
+    }
+
+    /// Number of transactions the oldest reader lags behind.
+    ///
+    /// A large lag prevents the GC from reclaiming pages.
+    pub fn reader_lag(&self) -> u64 {
+        self.recent_txnid.saturating_sub(self.latter_reader_txnid)
+    }
+
+    /// Index of the meta page holding the most recent transaction.
+    pub fn active_meta(&self) -> usize {
+        (0..3).max_by_key(|&i| self.meta_txnid[i]).unwrap_or(0)
+    }
+}
+
+impl From<MDBX_envinfo> for EnvInfo {
+    fn from(info: MDBX_envinfo) -> Self {
+        EnvInfo {
+            geometry: GeometryInfo {
+                lower: info.mi_geo.lower,
+                upper: info.mi_geo.upper,
+                current: info.mi_geo.current,
+                shrink: info.mi_geo.shrink,
+                grow: info.mi_geo.grow,
+            },
+            map_size: info.mi_mapsize,
+            file_size: info.mi_dxb_fsize,
+            file_allocated: info.mi_dxb_fallocated,
+            last_pgno: info.mi_last_pgno,
+            recent_txnid: info.mi_recent_txnid,
+            latter_reader_txnid: info.mi_latter_reader_txnid,
+            self_latter_reader_txnid: info.mi_self_latter_reader_txnid,
+            meta_txnid: info.mi_meta_txnid,
+            meta_sign: info.mi_meta_sign,
+            max_readers: info.mi_maxreaders,
+            num_readers: info.mi_numreaders,
+            page_size: info.mi_dxb_pagesize,
+            sys_page_size: info.mi_sys_pagesize,
+            unsync_volume: info.mi_unsync_volume,
+            autosync_threshold: info.mi_autosync_threshold,
+            since_sync: duration_from_16dot16(info.mi_since_sync_seconds16dot16 as u64),
+            autosync_period: duration_from_16dot16(info.mi_autosync_period_seconds16dot16 as u64),
+            since_reader_check: duration_from_16dot16(
+                info.mi_since_reader_check_seconds16dot16 as u64,
+            ),
+            mode: info.mi_mode,
+            pgop_stat: info.mi_pgop_stat,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stat_totals() {
+        let stat = Stat::from(MDBX_stat {
+            ms_psize: 4096,
+            ms_depth: 2,
+            ms_branch_pages: 1,
+            ms_leaf_pages: 10,
+            ms_overflow_pages: 5,
+            ms_entries: 100,
+            ms_mod_txnid: 7,
+        });
+        assert_eq!(stat.total_pages(), 16);
+        assert_eq!(stat.total_bytes(), 16 * 4096);
+    }
+
+    #[test]
+    fn test_envinfo_derived() {
+        let mut raw = MDBX_envinfo::default();
+        raw.mi_geo.current = 1 << 20;
+        raw.mi_geo.upper = 1 << 30;
+        raw.mi_dxb_pagesize = 4096;
+        raw.mi_last_pgno = 127;
+        raw.mi_recent_txnid = 42;
+        raw.mi_latter_reader_txnid = 40;
+        raw.mi_meta_txnid = [41, 42, 40];
+        raw.mi_since_sync_seconds16dot16 = 3 << 15;
+        let info = EnvInfo::from(raw);
+
+        assert_eq!(info.used_bytes(), 128 * 4096);
+        assert_eq!(info.fill_ratio(), 0.5);
+        assert_eq!(info.free_space_estimate(), (1 << 30) - 128 * 4096);
+        assert_eq!(info.reader_lag(), 2);
+        assert_eq!(info.active_meta(), 1);
+        assert_eq!(info.since_sync, Duration::from_millis(1500));
+    }
+}
@@ -0,0 +1,445 @@
+//! Range and prefix iteration over a database.
+
+use std::cmp::Ordering;
+use std::iter::FusedIterator;
+use std::ops::{Bound, RangeBounds};
+
+use crate::cursor::{Cursor, Item};
+use crate::database::Database;
+use crate::error::Result;
+use crate::flags::DbFlags;
+use crate::ordering::KeyOrdering;
+use crate::trace::{self, Span};
+use crate::transaction::Txn;
+use crate::MDBX_cursor_op::*;
+
+/// Owned key bounds, compared in table order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct KeyRange {
+    start: Bound<Vec<u8>>,
+    end: Bound<Vec<u8>>,
+}
+
+impl KeyRange {
+    pub(crate) fn new<'k, R: RangeBounds<&'k [u8]>>(range: &R) -> Self {
+        let owned = |bound: Bound<&&[u8]>| bound.map(|key| key.to_vec());
+        KeyRange {
+            start: owned(range.start_bound()),
+            end: owned(range.end_bound()),
+        }
+    }
+
+    pub(crate) fn from_bounds(start: Bound<Vec<u8>>, end: Bound<Vec<u8>>) -> Self {
+        KeyRange { start, end }
+    }
+
+    /// Keys starting with `prefix` in lexicographic order.
+    pub(crate) fn prefix(prefix: &[u8]) -> Self {
+        KeyRange {
+            start: Bound::Included(prefix.to_vec()),
+            end: prefix_successor(prefix).map_or(Bound::Unbounded, Bound::Excluded),
+        }
+    }
+
+    pub(crate) fn full() -> Self {
+        KeyRange {
+            start: Bound::Unbounded,
+            end: Bound::Unbounded,
+        }
+    }
+
+    pub(crate) fn start(&self) -> Bound<&[u8]> {
+        self.start.as_ref().map(Vec::as_slice)
+    }
+
+    /// Check that the bounds can be compared with `ordering`.
+    pub(crate) fn check(&self, ordering: &KeyOrdering) -> Result<()> {
+        for bound in [&self.start, &self.end] {
+            if let Bound::Included(key) | Bound::Excluded(key) = bound {
+                ordering.check(key)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether `key` is not past the end bound.
+    pub(crate) fn before_end(&self, key: &[u8], cmp: impl Fn(&[u8], &[u8]) -> Ordering) -> bool {
+        match &self.end {
+            Bound::Included(end) => cmp(key, end) != Ordering::Greater,
+            Bound::Excluded(end) => cmp(key, end) == Ordering::Less,
+            Bound::Unbounded => true,
+        }
+    }
+
+    /// Whether `key` is not before the start bound.
+    pub(crate) fn after_start(&self, key: &[u8], cmp: impl Fn(&[u8], &[u8]) -> Ordering) -> bool {
+        match &self.start {
+            Bound::Included(start) => cmp(key, start) != Ordering::Less,
+            Bound::Excluded(start) => cmp(key, start) == Ordering::Greater,
+            Bound::Unbounded => true,
+        }
+    }
+}
+
+/// Smallest key greater than every key starting with `prefix`, if any.
+fn prefix_successor(prefix: &[u8]) -> Option<Vec<u8>> {
+    let last = prefix.iter().rposition(|&byte| byte != 0xff)?;
+    let mut successor = prefix[..=last].to_vec();
+    successor[last] += 1;
+    Some(successor)
+}
+
+/// Double-ended iterator over the entries of a database within a key range.
+///
+/// Created by [`Txn::iter`], [`Txn::range`] and [`Txn::prefix`]. Bounds are
+/// interpreted in table order, so ranges over `MDBX_REVERSEKEY` and
+/// `MDBX_INTEGERKEY` tables follow the engine's comparator. For
+/// `MDBX_DUPSORT` tables every duplicate is yielded as its own entry.
+#[derive(Debug)]
+pub struct Iter<'txn, T: Txn + ?Sized> {
+    front: Cursor<'txn, T>,
+    back: Option<Cursor<'txn, T>>,
+    range: KeyRange,
+    /// Keys must start with this; set when such keys aren't adjacent.
+    filter: Option<Vec<u8>>,
+    key_cmp: KeyOrdering,
+    data_cmp: Option<KeyOrdering>,
+    front_started: bool,
+    done: bool,
+    entries: u64,
+    span: Span,
+}
+
+impl<'txn, T: Txn + ?Sized> Iter<'txn, T> {
+    pub(crate) fn new(txn: &'txn T, db: Database, range: KeyRange) -> Result<Self> {
+        let dupsort = db.flags().contains(DbFlags::DUPSORT);
+        let key_cmp = KeyOrdering::for_db(db)?;
+        range.check(&key_cmp)?;
+        Ok(Iter {
+            front: Cursor::open(txn, db)?,
+            back: None,
+            range,
+            filter: None,
+            key_cmp,
+            data_cmp: dupsort
+                .then(|| KeyOrdering::for_dup_flags(db.flags()))
+                .transpose()?,
+            front_started: false,
+            done: false,
+            entries: 0,
+            span: trace::scan_span(db.dbi()),
+        })
+    }
+
+    pub(crate) fn prefix(txn: &'txn T, db: Database, prefix: &[u8]) -> Result<Self> {
+        // Only a bytewise comparator keeps keys with a common prefix together
+        if db
+            .flags()
+            .intersects(DbFlags::REVERSEKEY | DbFlags::INTEGERKEY)
+        {
+            let mut iter = Iter::new(txn, db, KeyRange::full())?;
+            iter.filter = Some(prefix.to_vec());
+            Ok(iter)
+        } else {
+            Iter::new(txn, db, KeyRange::prefix(prefix))
+        }
+    }
+
+    fn compare_items(&self, a: &Item<'_>, b: &Item<'_>) -> Ordering {
+        self.key_cmp.order_fn()(&a.0, &b.0).then_with(|| {
+            self.data_cmp
+                .map_or(Ordering::Equal, |cmp| cmp.order_fn()(&a.1, &b.1))
+        })
+    }
+
+    fn matches_filter(&self, key: &[u8]) -> bool {
+        self.filter
+            .as_ref()
+            .is_none_or(|prefix| key.starts_with(prefix))
+    }
+
+    fn next_front(&mut self) -> Result<Option<Item<'txn>>> {
+        loop {
+            let item = if self.front_started {
+                self.front.next()?
+            } else {
+                self.front_started = true;
+                seek_front(&self.range, self.data_cmp.is_some(), &mut self.front)?
+            };
+            let Some(item) = item else {
+                return Ok(None);
+            };
+            if !self.range.before_end(&item.0, self.key_cmp.order_fn()) {
+                return Ok(None);
+            }
+            if let Some(back) = &mut self.back {
+                if let Some(back) = back.current()? {
+                    if self.compare_items(&item, &back) != Ordering::Less {
+                        return Ok(None);
+                    }
+                }
+            }
+            if self.matches_filter(&item.0) {
+                return Ok(Some(item));
+            }
+        }
+    }
+
+    fn next_back_item(&mut self) -> Result<Option<Item<'txn>>> {
+        loop {
+            let item = match &mut self.back {
+                Some(cursor) => cursor.prev()?,
+                None => {
+                    let mut cursor = Cursor::open(self.front.txn(), self.front.db())?;
+                    let item = seek_back(&self.range, self.data_cmp.is_some(), &mut cursor)?;
+                    self.back = Some(cursor);
+                    item
+                }
+            };
+            let Some(item) = item else {
+                return Ok(None);
+            };
+            if !self.range.after_start(&item.0, self.key_cmp.order_fn()) {
+                return Ok(None);
+            }
+            if self.front_started {
+                if let Some(front) = self.front.current()? {
+                    if self.compare_items(&item, &front) != Ordering::Greater {
+                        return Ok(None);
+                    }
+                }
+            }
+            if self.matches_filter(&item.0) {
+                return Ok(Some(item));
+            }
+        }
+    }
+
+    /// Count the entry and turn the outcome into an iterator item.
+    fn finish(&mut self, item: Result<Option<Item<'txn>>>) -> Option<Result<Item<'txn>>> {
+        match item {
+            Ok(Some(item)) => {
+                self.entries += 1;
+                Some(Ok(item))
+            }
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            Err(err) => {
+                self.done = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+/// Position `cursor` at the first entry of `range`.
+fn seek_front<'txn, T: Txn + ?Sized>(
+    range: &KeyRange,
+    dupsort: bool,
+    cursor: &mut Cursor<'txn, T>,
+) -> Result<Option<Item<'txn>>> {
+    let item = match &range.start {
+        Bound::Unbounded => return cursor.first(),
+        Bound::Included(key) => return cursor.get(MDBX_SET_LOWERBOUND, Some(key), None),
+        Bound::Excluded(key) => cursor.get(MDBX_TO_KEY_GREATER_THAN, Some(key), None)?,
+    };
+    match item {
+        Some(_) if dupsort => cursor.get(MDBX_FIRST_DUP, None, None),
+        item => Ok(item),
+    }
+}
+
+/// Position `cursor` at the last entry of `range`.
+fn seek_back<'txn, T: Txn + ?Sized>(
+    range: &KeyRange,
+    dupsort: bool,
+    cursor: &mut Cursor<'txn, T>,
+) -> Result<Option<Item<'txn>>> {
+    let item = match &range.end {
+        Bound::Unbounded => return cursor.last(),
+        Bound::Included(key) => cursor.get(MDBX_TO_KEY_LESSER_OR_EQUAL, Some(key), None)?,
+        Bound::Excluded(key) => cursor.get(MDBX_TO_KEY_LESSER_THAN, Some(key), None)?,
+    };
+    match item {
+        Some(_) if dupsort => cursor.get(MDBX_LAST_DUP, None, None),
+        item => Ok(item),
+    }
+}
+
+impl<'txn, T: Txn + ?Sized> Iterator for Iter<'txn, T> {
+    type Item = Result<Item<'txn>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let item = self.next_front();
+        self.finish(item)
+    }
+}
+
+impl<T: Txn + ?Sized> DoubleEndedIterator for Iter<'_, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let item = self.next_back_item();
+        self.finish(item)
+    }
+}
+
+impl<T: Txn + ?Sized> FusedIterator for Iter<'_, T> {}
+
+impl<T: Txn + ?Sized> Drop for Iter<'_, T> {
+    fn drop(&mut self) {
+        trace::record_scan(&self.span, self.entries);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flags::PutFlags;
+    use crate::test_util::TestEnv;
+    use crate::transaction::RwTxn;
+
+    fn lexicographic(a: &[u8], b: &[u8]) -> Ordering {
+        a.cmp(b)
+    }
+
+    #[test]
+    fn test_prefix_successor() {
+        assert_eq!(prefix_successor(b"user:"), Some(b"user;".to_vec()));
+        assert_eq!(prefix_successor(b"a\xff\xff"), Some(b"b".to_vec()));
+        assert_eq!(prefix_successor(b"\xff\xff"), None);
+        assert_eq!(prefix_successor(b""), None);
+    }
+
+    #[test]
+    fn test_key_range_bounds() {
+        let (b, d): (&[u8], &[u8]) = (b"b", b"d");
+        let range = KeyRange::new(&(b..d));
+        assert!(!range.after_start(b"a", lexicographic));
+        assert!(range.after_start(b"b", lexicographic));
+        assert!(range.before_end(b"c\xff", lexicographic));
+        assert!(!range.before_end(b"d", lexicographic));
+
+        let range = KeyRange::new(&(Bound::Excluded(b), Bound::Included(d)));
+        assert!(!range.after_start(b"b", lexicographic));
+        assert!(range.after_start(b"ba", lexicographic));
+        assert!(range.before_end(b"d", lexicographic));
+        assert!(!range.before_end(b"da", lexicographic));
+
+        assert_eq!(KeyRange::new(&(..)), KeyRange::full());
+    }
+
+    #[test]
+    fn test_prefix_range() {
+        let range = KeyRange::prefix(b"user:");
+        for key in [&b"user:"[..], b"user:1", b"user:\xff\xff"] {
+            assert!(range.after_start(key, lexicographic));
+            assert!(range.before_end(key, lexicographic));
+        }
+        assert!(!range.after_start(b"user", lexicographic));
+        assert!(!range.before_end(b"user;", lexicographic));
+        assert!(KeyRange::prefix(b"\xff").before_end(b"\xff\xff", lexicographic));
+    }
+
+    fn keys<'txn>(iter: impl Iterator<Item = Result<Item<'txn>>>) -> Vec<Vec<u8>> {
+        iter.map(|item| item.unwrap().0.to_vec()).collect()
+    }
+
+    fn fill(txn: &RwTxn<'_>, name: &str, flags: DbFlags, keys: &[&str]) -> Database {
+        let db = txn.create_db(Some(name), flags).unwrap();
+        for key in keys {
+            txn.put(db, key.as_bytes(), b"", PutFlags::empty()).unwrap();
+        }
+        db
+    }
+
+    #[test]
+    fn test_meet_in_the_middle() {
+        let env = TestEnv::new();
+        let txn = env.begin_rw_txn().unwrap();
+        for count in 0..6 {
+            let names: Vec<_> = (0..count).map(|i| format!("k{}", i)).collect();
+            let names: Vec<_> = names.iter().map(String::as_str).collect();
+            let db = fill(&txn, &format!("t{}", count), DbFlags::empty(), &names);
+
+            let mut iter = txn.iter(db).unwrap();
+            let (mut front, mut back) = (Vec::new(), Vec::new());
+            while let Some(item) = iter.next() {
+                front.push(item.unwrap().0.to_vec());
+                match iter.next_back() {
+                    Some(item) => back.push(item.unwrap().0.to_vec()),
+                    None => break,
+                }
+            }
+            assert!(iter.next().is_none() && iter.next_back().is_none());
+            front.extend(back.into_iter().rev());
+            let expected: Vec<_> = names.iter().map(|name| name.as_bytes().to_vec()).collect();
+            assert_eq!(front, expected);
+        }
+    }
+
+    #[test]
+    fn test_meet_within_duplicates() {
+        let env = TestEnv::new();
+        let txn = env.begin_rw_txn().unwrap();
+        let db = txn.create_db(Some("dups"), DbFlags::DUPSORT).unwrap();
+        for data in [b"1", b"2", b"3"] {
+            txn.put(db, b"k", data, PutFlags::empty()).unwrap();
+        }
+
+        let mut iter = txn.iter(db).unwrap();
+        assert_eq!(&*iter.next().unwrap().unwrap().1, b"1");
+        assert_eq!(&*iter.next_back().unwrap().unwrap().1, b"3");
+        assert_eq!(&*iter.next_back().unwrap().unwrap().1, b"2");
+        assert!(iter.next().is_none());
+        assert!(iter.next_back().is_none());
+    }
+
+    #[test]
+    fn test_excluded_bounds() {
+        let env = TestEnv::new();
+        let txn = env.begin_rw_txn().unwrap();
+        let db = fill(&txn, "t", DbFlags::empty(), &["a", "b", "c", "d", "e"]);
+        let (b, e): (&[u8], &[u8]) = (b"b", b"e");
+
+        let range = (Bound::Excluded(b), Bound::Excluded(e));
+        assert_eq!(keys(txn.range(db, range).unwrap()), [b"c", b"d"]);
+        assert_eq!(keys(txn.range(db, range).unwrap().rev()), [b"d", b"c"]);
+        let range = (Bound::Excluded(b), Bound::Unbounded);
+        assert_eq!(keys(txn.range(db, range).unwrap()), [b"c", b"d", b"e"]);
+        let range = (Bound::Unbounded, Bound::Excluded(b));
+        assert_eq!(keys(txn.range(db, range).unwrap().rev()), [b"a"]);
+        // Bounds between keys
+        let (bb, dd): (&[u8], &[u8]) = (b"bb", b"dd");
+        let range = (Bound::Excluded(bb), Bound::Excluded(dd));
+        assert_eq!(keys(txn.range(db, range).unwrap()), [b"c", b"d"]);
+        assert_eq!(keys(txn.range(db, range).unwrap().rev()), [b"d", b"c"]);
+    }
+
+    #[test]
+    fn test_prefix_on_reverse_key() {
+        let env = TestEnv::new();
+        let txn = env.begin_rw_txn().unwrap();
+        let names = ["user:1", "auser:1", "other:1", "user:2", "user", "xuser:2"];
+        let db = fill(&txn, "t", DbFlags::REVERSEKEY, &names);
+
+        // Compared from the last byte, so the matches aren't adjacent
+        let expected: [&[u8]; 6] = [
+            b"other:1", b"user:1", b"auser:1", b"user:2", b"xuser:2", b"user",
+        ];
+        assert_eq!(keys(txn.iter(db).unwrap()), expected);
+        let iter = txn.prefix(db, b"user:").unwrap();
+        assert!(iter.filter.is_some());
+        assert_eq!(keys(iter), [b"user:1", b"user:2"]);
+        assert_eq!(
+            keys(txn.prefix(db, b"user:").unwrap().rev()),
+            [b"user:2", b"user:1"]
+        );
+    }
+}
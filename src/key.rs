@@ -0,0 +1,315 @@
+//! Order-preserving key encodings for numbers.
+
+use std::mem::size_of;
+
+use crate::constants::MDBX_BAD_VALSIZE;
+use crate::error::{Error, Result};
+use crate::*;
+
+/// Types with a byte encoding whose lexicographic order matches their
+/// numeric order.
+///
+/// Signed integers and floats go through the engine's order-preserving
+/// helpers (`mdbx_key_from_int64`, `mdbx_key_from_double`, ...) and are
+/// stored big-endian, so keys sort correctly with the default comparator and
+/// without `MDBX_INTEGERKEY`'s native-endian alignment constraints.
+pub trait OrderedKey: Sized {
+    /// Encoded key bytes.
+    type Bytes: AsRef<[u8]>;
+
+    /// Encode the value as an order-preserving key.
+    fn to_key(&self) -> Self::Bytes;
+
+    /// Decode a key produced by [`to_key`](OrderedKey::to_key).
+    ///
+    /// Fails with `MDBX_BAD_VALSIZE` if `key` has the wrong length.
+    fn from_key(key: &[u8]) -> Result<Self>;
+}
+
+fn key_array<const N: usize>(key: &[u8]) -> Result<[u8; N]> {
+    key.try_into()
+        .map_err(|_| Error::from_code(MDBX_BAD_VALSIZE))
+}
+
+/// Pass a decoded key to one of the `mdbx_*_from_key` functions, which take
+/// the value in native byte order.
+fn native_val<T>(bytes: &T) -> MDBX_val {
+    MDBX_val {
+        iov_base: bytes as *const T as *mut _,
+        iov_len: size_of::<T>(),
+    }
+}
+
+impl OrderedKey for u8 {
+    type Bytes = [u8; 1];
+
+    #[inline]
+    fn to_key(&self) -> [u8; 1] {
+        [*self]
+    }
+
+    #[inline]
+    fn from_key(key: &[u8]) -> Result<Self> {
+        Ok(u8::from_be_bytes(key_array(key)?))
+    }
+}
+
+impl OrderedKey for u16 {
+    type Bytes = [u8; 2];
+
+    #[inline]
+    fn to_key(&self) -> [u8; 2] {
+        self.to_be_bytes()
+    }
+
+    #[inline]
+    fn from_key(key: &[u8]) -> Result<Self> {
+        Ok(u16::from_be_bytes(key_array(key)?))
+    }
+}
+
+impl OrderedKey for u32 {
+    type Bytes = [u8; 4];
+
+    #[inline]
+    fn to_key(&self) -> [u8; 4] {
+        self.to_be_bytes()
+    }
+
+    #[inline]
+    fn from_key(key: &[u8]) -> Result<Self> {
+        Ok(u32::from_be_bytes(key_array(key)?))
+    }
+}
+
+impl OrderedKey for u64 {
+    type Bytes = [u8; 8];
+
+    #[inline]
+    fn to_key(&self) -> [u8; 8] {
+        self.to_be_bytes()
+    }
+
+    #[inline]
+    fn from_key(key: &[u8]) -> Result<Self> {
+        Ok(u64::from_be_bytes(key_array(key)?))
+    }
+}
+
+impl OrderedKey for u128 {
+    type Bytes = [u8; 16];
+
+    #[inline]
+    fn to_key(&self) -> [u8; 16] {
+        self.to_be_bytes()
+    }
+
+    #[inline]
+    fn from_key(key: &[u8]) -> Result<Self> {
+        Ok(u128::from_be_bytes(key_array(key)?))
+    }
+}
+
+impl OrderedKey for i32 {
+    type Bytes = [u8; 4];
+
+    #[inline]
+    fn to_key(&self) -> [u8; 4] {
+        mdbx_key_from_int32(*self).to_be_bytes()
+    }
+
+    fn from_key(key: &[u8]) -> Result<Self> {
+        let key = u32::from_key(key)?;
+        Ok(unsafe { mdbx_int32_from_key(native_val(&key)) })
+    }
+}
+
+impl OrderedKey for i64 {
+    type Bytes = [u8; 8];
+
+    #[inline]
+    fn to_key(&self) -> [u8; 8] {
+        mdbx_key_from_int64(*self).to_be_bytes()
+    }
+
+    fn from_key(key: &[u8]) -> Result<Self> {
+        let key = u64::from_key(key)?;
+        Ok(unsafe { mdbx_int64_from_key(native_val(&key)) })
+    }
+}
+
+impl OrderedKey for f32 {
+    type Bytes = [u8; 4];
+
+    fn to_key(&self) -> [u8; 4] {
+        unsafe { mdbx_key_from_ptrfloat(self) }.to_be_bytes()
+    }
+
+    fn from_key(key: &[u8]) -> Result<Self> {
+        let key = u32::from_key(key)?;
+        Ok(unsafe { mdbx_float_from_key(native_val(&key)) })
+    }
+}
+
+impl OrderedKey for f64 {
+    type Bytes = [u8; 8];
+
+    fn to_key(&self) -> [u8; 8] {
+        unsafe { mdbx_key_from_ptrdouble(self) }.to_be_bytes()
+    }
+
+    fn from_key(key: &[u8]) -> Result<Self> {
+        let key = u64::from_key(key)?;
+        Ok(unsafe { mdbx_double_from_key(native_val(&key)) })
+    }
+}
+
+/// An integer key that sorts together with `f64` keys, for indexes over
+/// JSON numbers.
+///
+/// Exact within RFC 7159's integer range of ±(2^53 - 1); larger values are
+/// rounded like their `f64` conversion. Decoding the key of a non-integral
+/// `f64` truncates toward zero.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct JsonInteger(pub i64);
+
+impl OrderedKey for JsonInteger {
+    type Bytes = [u8; 8];
+
+    fn to_key(&self) -> [u8; 8] {
+        unsafe { mdbx_key_from_jsonInteger(self.0) }.to_be_bytes()
+    }
+
+    fn from_key(key: &[u8]) -> Result<Self> {
+        let key = u64::from_key(key)?;
+        // The engine decodes the key of zero as `INT64_MAX`
+        if key == mdbx_key_from_int64(0) {
+            return Ok(JsonInteger(0));
+        }
+        Ok(JsonInteger(unsafe {
+            mdbx_jsonInteger_from_key(native_val(&key))
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_int_key_order() {
+        let values = [i64::MIN, -1_000_000, -1, 0, 1, 42, i64::MAX];
+        for pair in values.windows(2) {
+            assert!(pair[0].to_key() < pair[1].to_key());
+        }
+        let values = [i32::MIN, -7, -1, 0, 1, i32::MAX];
+        for pair in values.windows(2) {
+            assert!(pair[0].to_key() < pair[1].to_key());
+        }
+    }
+
+    #[test]
+    fn test_unsigned_key_roundtrip() {
+        for value in [0u64, 1, 255, 256, u64::MAX] {
+            assert_eq!(u64::from_key(&value.to_key()), Ok(value));
+        }
+        assert!(1u32.to_key() < 256u32.to_key());
+        assert_eq!(
+            u32::from_key(&[0, 1]),
+            Err(Error::from_code(MDBX_BAD_VALSIZE))
+        );
+    }
+
+    #[test]
+    fn test_signed_key_roundtrip() {
+        for value in [i64::MIN, -1_000_000, -1, 0, 1, i64::MAX] {
+            assert_eq!(i64::from_key(&value.to_key()), Ok(value));
+        }
+        for value in [i32::MIN, -7, -1, 0, 1, i32::MAX] {
+            assert_eq!(i32::from_key(&value.to_key()), Ok(value));
+        }
+        assert_eq!(
+            i64::from_key(&[0; 4]),
+            Err(Error::from_code(MDBX_BAD_VALSIZE))
+        );
+        assert_eq!(
+            i32::from_key(&[0; 8]),
+            Err(Error::from_code(MDBX_BAD_VALSIZE))
+        );
+    }
+
+    #[test]
+    fn test_float_keys() {
+        let values = [
+            f64::NEG_INFINITY,
+            f64::MIN,
+            -1e10,
+            -1.5,
+            -f64::MIN_POSITIVE,
+            0.0,
+            f64::MIN_POSITIVE,
+            1.0,
+            1.5,
+            f64::MAX,
+            f64::INFINITY,
+        ];
+        for pair in values.windows(2) {
+            assert!(pair[0].to_key() < pair[1].to_key(), "{:?}", pair);
+        }
+        for value in values {
+            assert_eq!(f64::from_key(&value.to_key()), Ok(value));
+        }
+        // Negative zero sorts right before zero and keeps its sign
+        assert!((-f64::MIN_POSITIVE).to_key() < (-0.0f64).to_key());
+        assert!((-0.0f64).to_key() < 0.0f64.to_key());
+        let zero = f64::from_key(&(-0.0f64).to_key()).unwrap();
+        assert_eq!(zero.to_bits(), (-0.0f64).to_bits());
+
+        let values = [
+            f32::NEG_INFINITY,
+            f32::MIN,
+            -2.5,
+            -f32::MIN_POSITIVE,
+            0.0,
+            f32::MIN_POSITIVE,
+            2.5,
+            f32::MAX,
+            f32::INFINITY,
+        ];
+        for pair in values.windows(2) {
+            assert!(pair[0].to_key() < pair[1].to_key(), "{:?}", pair);
+        }
+        for value in values {
+            assert_eq!(f32::from_key(&value.to_key()), Ok(value));
+        }
+        assert!((-0.0f32).to_key() < 0.0f32.to_key());
+        let zero = f32::from_key(&(-0.0f32).to_key()).unwrap();
+        assert_eq!(zero.to_bits(), (-0.0f32).to_bits());
+        assert_eq!(
+            f32::from_key(&[0; 8]),
+            Err(Error::from_code(MDBX_BAD_VALSIZE))
+        );
+    }
+
+    #[test]
+    fn test_json_integer_keys() {
+        let limit = (1i64 << 53) - 1;
+        for value in [-limit, -1_000, -1, 0, 1, 1_000, limit] {
+            let key = JsonInteger(value).to_key();
+            assert_eq!(JsonInteger::from_key(&key), Ok(JsonInteger(value)));
+            // Interleaves with the keys of doubles
+            assert_eq!(key, (value as f64).to_key());
+            assert!((value as f64 - 0.5).to_key() < key);
+            assert!(key < (value as f64 + 0.5).to_key());
+        }
+        assert_eq!(JsonInteger::from_key(&2.75f64.to_key()), Ok(JsonInteger(2)));
+        assert_eq!(
+            JsonInteger::from_key(&(-0.0f64).to_key()),
+            Ok(JsonInteger(0))
+        );
+        assert_eq!(
+            JsonInteger::from_key(&[0; 4]),
+            Err(Error::from_code(MDBX_BAD_VALSIZE))
+        );
+    }
+}
@@ -0,0 +1,360 @@
+//! Commit latency breakdown and rolling latency histograms.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use crate::options::duration_from_16dot16;
+use crate::MDBX_commit_latency;
+
+/// Phase of a commit as reported by `mdbx_txn_commit_ex`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CommitPhase {
+    Preparation,
+    GcWallclock,
+    Audit,
+    Write,
+    Sync,
+    Ending,
+    Whole,
+    GcCputime,
+}
+
+impl CommitPhase {
+    /// All phases, in `MDBX_commit_latency` field order.
+    pub const ALL: [CommitPhase; 8] = [
+        CommitPhase::Preparation,
+        CommitPhase::GcWallclock,
+        CommitPhase::Audit,
+        CommitPhase::Write,
+        CommitPhase::Sync,
+        CommitPhase::Ending,
+        CommitPhase::Whole,
+        CommitPhase::GcCputime,
+    ];
+
+    /// Field name in `MDBX_commit_latency`.
+    pub fn name(self) -> &'static str {
+        match self {
+            CommitPhase::Preparation => "preparation",
+            CommitPhase::GcWallclock => "gc_wallclock",
+            CommitPhase::Audit => "audit",
+            CommitPhase::Write => "write",
+            CommitPhase::Sync => "sync",
+            CommitPhase::Ending => "ending",
+            CommitPhase::Whole => "whole",
+            CommitPhase::GcCputime => "gc_cputime",
+        }
+    }
+}
+
+/// Time spent in each phase of a commit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CommitLatency {
+    /// Preparing the commit, before GC update.
+    pub preparation: Duration,
+    /// Updating the GC, wall clock time.
+    pub gc_wallclock: Duration,
+    /// Internal audit, if enabled.
+    pub audit: Duration,
+    /// Writing dirty pages.
+    pub write: Duration,
+    /// Syncing to disk.
+    pub sync: Duration,
+    /// Releasing resources.
+    pub ending: Duration,
+    /// Whole commit.
+    pub whole: Duration,
+    /// Updating the GC, CPU time.
+    pub gc_cputime: Duration,
+}
+
+impl CommitLatency {
+    /// Time spent in `phase`.
+    pub fn get(&self, phase: CommitPhase) -> Duration {
+        match phase {
+            CommitPhase::Preparation => self.preparation,
+            CommitPhase::GcWallclock => self.gc_wallclock,
+            CommitPhase::Audit => self.audit,
+            CommitPhase::Write => self.write,
+            CommitPhase::Sync => self.sync,
+            CommitPhase::Ending => self.ending,
+            CommitPhase::Whole => self.whole,
+            CommitPhase::GcCputime => self.gc_cputime,
+        }
+    }
+}
+
+impl From<MDBX_commit_latency> for CommitLatency {
+    fn from(latency: MDBX_commit_latency) -> Self {
+        let decode = |raw: u32| duration_from_16dot16(raw as u64);
+        CommitLatency {
+            preparation: decode(latency.preparation),
+            gc_wallclock: decode(latency.gc_wallclock),
+            audit: decode(latency.audit),
+            write: decode(latency.write),
+            sync: decode(latency.sync),
+            ending: decode(latency.ending),
+            whole: decode(latency.whole),
+            gc_cputime: decode(latency.gc_cputime),
+        }
+    }
+}
+
+/// Values below this many microseconds get one bucket each.
+const LINEAR_BUCKETS: usize = 16;
+/// Sub-buckets per power of two above the linear range (3 significant bits).
+const SUB_BUCKET_BITS: u32 = 3;
+const SUB_BUCKETS: usize = 1 << SUB_BUCKET_BITS;
+const LINEAR_BITS: u32 = LINEAR_BUCKETS.trailing_zeros();
+const BUCKETS: usize = LINEAR_BUCKETS + (64 - LINEAR_BITS as usize) * SUB_BUCKETS;
+
+/// Bucket for a value in microseconds.
+fn bucket_index(micros: u64) -> usize {
+    if micros < LINEAR_BUCKETS as u64 {
+        return micros as usize;
+    }
+    let exp = 63 - micros.leading_zeros();
+    let sub = (micros >> (exp - SUB_BUCKET_BITS)) as usize & (SUB_BUCKETS - 1);
+    LINEAR_BUCKETS + (exp - LINEAR_BITS) as usize * SUB_BUCKETS + sub
+}
+
+/// Largest value in microseconds that falls into `index`.
+fn bucket_upper_bound(index: usize) -> u64 {
+    if index < LINEAR_BUCKETS {
+        return index as u64;
+    }
+    let exp = ((index - LINEAR_BUCKETS) / SUB_BUCKETS) as u32 + LINEAR_BITS;
+    let sub = ((index - LINEAR_BUCKETS) % SUB_BUCKETS) as u64;
+    let width = 1u64 << (exp - SUB_BUCKET_BITS);
+    let lower = (1u64 << exp) + sub * width;
+    lower.saturating_add(width - 1)
+}
+
+/// Lock-free latency histogram with HDR-style log-linear buckets.
+///
+/// Values are recorded in microseconds with about 12% relative precision.
+#[derive(Debug)]
+pub struct LatencyHistogram {
+    buckets: Box<[AtomicU64]>,
+    count: AtomicU64,
+    sum_micros: AtomicU64,
+    max_micros: AtomicU64,
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        LatencyHistogram {
+            buckets: (0..BUCKETS).map(|_| AtomicU64::new(0)).collect(),
+            count: AtomicU64::new(0),
+            sum_micros: AtomicU64::new(0),
+            max_micros: AtomicU64::new(0),
+        }
+    }
+}
+
+impl LatencyHistogram {
+    /// Record one sample.
+    pub fn record(&self, value: Duration) {
+        let micros = value.as_micros().min(u64::MAX as u128) as u64;
+        self.buckets[bucket_index(micros)].fetch_add(1, Ordering::Relaxed);
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.sum_micros.fetch_add(micros, Ordering::Relaxed);
+        self.max_micros.fetch_max(micros, Ordering::Relaxed);
+    }
+
+    /// Copy of the current counts.
+    pub fn snapshot(&self) -> HistogramSnapshot {
+        self.collect(|counter| counter.load(Ordering::Relaxed))
+    }
+
+    /// Copy of the current counts, resetting the histogram.
+    ///
+    /// Calling this once per reporting interval gives a rolling window.
+    pub fn take(&self) -> HistogramSnapshot {
+        self.collect(|counter| counter.swap(0, Ordering::Relaxed))
+    }
+
+    fn collect(&self, read: impl Fn(&AtomicU64) -> u64) -> HistogramSnapshot {
+        HistogramSnapshot {
+            counts: self.buckets.iter().map(&read).collect(),
+            count: read(&self.count),
+            sum_micros: read(&self.sum_micros),
+            max_micros: read(&self.max_micros),
+        }
+    }
+}
+
+/// Point-in-time copy of a [`LatencyHistogram`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HistogramSnapshot {
+    counts: Vec<u64>,
+    count: u64,
+    sum_micros: u64,
+    max_micros: u64,
+}
+
+impl HistogramSnapshot {
+    /// Number of samples.
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Sum of all samples.
+    pub fn sum(&self) -> Duration {
+        Duration::from_micros(self.sum_micros)
+    }
+
+    /// Largest sample.
+    pub fn max(&self) -> Duration {
+        Duration::from_micros(self.max_micros)
+    }
+
+    /// Mean of all samples.
+    pub fn mean(&self) -> Duration {
+        if self.count == 0 {
+            return Duration::ZERO;
+        }
+        Duration::from_micros(self.sum_micros / self.count)
+    }
+
+    /// Value below which `quantile` (in `0.0..=1.0`) of the samples fall.
+    pub fn quantile(&self, quantile: f64) -> Duration {
+        if self.count == 0 {
+            return Duration::ZERO;
+        }
+        let rank = ((quantile.clamp(0.0, 1.0) * self.count as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (index, &count) in self.counts.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                let bound = bucket_upper_bound(index).min(self.max_micros);
+                return Duration::from_micros(bound);
+            }
+        }
+        self.max()
+    }
+
+    /// Non-empty buckets as `(upper bound, cumulative count)`, in increasing order.
+    pub fn cumulative_buckets(&self) -> impl Iterator<Item = (Duration, u64)> + '_ {
+        let mut seen = 0;
+        self.counts
+            .iter()
+            .enumerate()
+            .filter(|(_, &count)| count > 0)
+            .map(move |(index, &count)| {
+                seen += count;
+                (Duration::from_micros(bucket_upper_bound(index)), seen)
+            })
+    }
+}
+
+/// Per-environment aggregator with one histogram per commit phase.
+///
+/// Enabled with
+/// [`EnvironmentBuilder::commit_latency_stats`](crate::EnvironmentBuilder::commit_latency_stats);
+/// every commit through the safe API is then recorded.
+#[derive(Debug, Default)]
+pub struct CommitLatencyStats {
+    phases: [LatencyHistogram; 8],
+}
+
+impl CommitLatencyStats {
+    /// Record the phases of one commit.
+    pub fn record(&self, latency: &CommitLatency) {
+        for (histogram, phase) in self.phases.iter().zip(CommitPhase::ALL) {
+            histogram.record(latency.get(phase));
+        }
+    }
+
+    /// Histogram of a single phase.
+    pub fn phase(&self, phase: CommitPhase) -> &LatencyHistogram {
+        let index = CommitPhase::ALL.iter().position(|&p| p == phase).unwrap();
+        &self.phases[index]
+    }
+
+    /// Snapshot of every phase.
+    pub fn snapshot(&self) -> Vec<(CommitPhase, HistogramSnapshot)> {
+        CommitPhase::ALL
+            .iter()
+            .zip(&self.phases)
+            .map(|(&phase, histogram)| (phase, histogram.snapshot()))
+            .collect()
+    }
+
+    /// Snapshot of every phase, resetting the histograms.
+    pub fn take(&self) -> Vec<(CommitPhase, HistogramSnapshot)> {
+        CommitPhase::ALL
+            .iter()
+            .zip(&self.phases)
+            .map(|(&phase, histogram)| (phase, histogram.take()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_commit_latency_from_raw() {
+        let latency = CommitLatency::from(MDBX_commit_latency {
+            sync: 1 << 16,
+            whole: 3 << 15,
+            ..Default::default()
+        });
+        assert_eq!(latency.sync, Duration::from_secs(1));
+        assert_eq!(latency.get(CommitPhase::Whole), Duration::from_millis(1500));
+        assert_eq!(latency.write, Duration::ZERO);
+    }
+
+    #[test]
+    fn test_bucket_bounds() {
+        for micros in [0u64, 1, 15, 16, 17, 100, 1_000, 65_535, 1 << 40, u64::MAX] {
+            let index = bucket_index(micros);
+            assert!(index < BUCKETS);
+            assert!(micros <= bucket_upper_bound(index), "{}", micros);
+            if index > 0 {
+                assert!(micros > bucket_upper_bound(index - 1), "{}", micros);
+            }
+        }
+    }
+
+    #[test]
+    fn test_histogram_quantiles() {
+        let histogram = LatencyHistogram::default();
+        for millis in 1..=100 {
+            histogram.record(Duration::from_millis(millis));
+        }
+        let snapshot = histogram.snapshot();
+        assert_eq!(snapshot.count(), 100);
+        assert_eq!(snapshot.max(), Duration::from_millis(100));
+        assert_eq!(snapshot.mean(), Duration::from_micros(50_500));
+
+        let p50 = snapshot.quantile(0.5).as_secs_f64();
+        assert!((0.050..0.057).contains(&p50), "{}", p50);
+        let p99 = snapshot.quantile(0.99).as_secs_f64();
+        assert!((0.099..=0.100).contains(&p99), "{}", p99);
+        assert_eq!(snapshot.cumulative_buckets().last().unwrap().1, 100);
+
+        assert_eq!(histogram.take().count(), 100);
+        assert_eq!(histogram.snapshot().count(), 0);
+    }
+
+    #[test]
+    fn test_commit_latency_stats() {
+        let stats = CommitLatencyStats::default();
+        let latency = CommitLatency {
+            sync: Duration::from_millis(5),
+            whole: Duration::from_millis(6),
+            ..Default::default()
+        };
+        stats.record(&latency);
+        stats.record(&latency);
+        assert_eq!(stats.phase(CommitPhase::Sync).snapshot().count(), 2);
+        let snapshot = stats.take();
+        assert_eq!(snapshot.len(), 8);
+        assert_eq!(snapshot[4].0, CommitPhase::Sync);
+        assert!(snapshot[4].1.max() >= Duration::from_millis(5));
+        assert_eq!(stats.phase(CommitPhase::Whole).snapshot().count(), 0);
+    }
+}
@@ -21,6 +21,18 @@
 //! }
 //! ```
 //!
+//! ## Safe API
+//!
+//! ```rust,ignore
+//! use mdbx_rs::*;
+//!
+//! let env = Environment::open("./mydb", EnvFlags::NOSUBDIR, SyncMode::Durable, 0o644)?;
+//! let txn = env.begin_rw_txn()?;
+//! let db = txn.create_db(None, DbFlags::empty())?;
+//! txn.put(db, b"hello", b"world", PutFlags::empty())?;
+//! txn.commit()?;
+//! ```
+//!
 //! ## Binary Compatibility
 //!
 //! This crate is binary compatible with C libmdbx databases.
@@ -35,6 +47,76 @@ use std::os::raw::{c_char, c_int, c_uint, c_void};
 pub mod constants;
 pub use constants::*;
 
+#[cfg(feature = "tokio")]
+mod async_env;
+mod bulk;
+mod changes;
+mod codec;
+mod cursor;
+mod database;
+mod environment;
+mod error;
+mod flags;
+mod geometry;
+mod growth;
+mod info;
+mod iter;
+mod key;
+mod latency;
+#[cfg(feature = "metrics")]
+mod metrics;
+mod multimap;
+mod options;
+mod ordering;
+mod pool;
+mod registry;
+mod retry;
+mod snapshot;
+mod table;
+#[cfg(test)]
+mod test_util;
+mod trace;
+mod transaction;
+mod value;
+mod version;
+
+#[cfg(feature = "tokio")]
+pub use async_env::{AsyncEnvironment, AsyncEnvironmentBuilder};
+pub use bulk::{BulkLoader, BulkStats};
+pub use changes::{ChangeFeed, CommitEvent, MAX_CAPTURED_KEYS};
+#[cfg(feature = "serde")]
+pub use codec::Postcard;
+pub use codec::{Codec, KeyCodec, ValueCodec};
+#[cfg(feature = "bytemuck")]
+pub use codec::{Pod, PodRef};
+pub use cursor::{Cursor, CursorMut, Item};
+pub use database::Database;
+pub use environment::{Environment, EnvironmentBuilder};
+pub use error::{liberr_message, strerror, Error, Result};
+pub use flags::{
+    CopyFlags, DbFlags, DebugFlags, EnvFlags, PutFlags, SyncMode, TxnFlags, WarmupFlags,
+};
+pub use geometry::{parse_size, Geometry};
+pub use growth::{MapGrowth, MapGrowthStats};
+pub use info::{EnvInfo, GeometryInfo, PageOpStat, Stat, TxnInfo};
+pub use iter::Iter;
+pub use key::{JsonInteger, OrderedKey};
+pub use latency::{
+    CommitLatency, CommitLatencyStats, CommitPhase, HistogramSnapshot, LatencyHistogram,
+};
+#[cfg(feature = "metrics")]
+pub use metrics::{MetricsCollector, MetricsSnapshot, MAIN_TABLE_LABEL};
+pub use multimap::{Dups, Keys, MultiMap};
+pub use options::{opt, EnvOption, EnvOptionKind, EnvOptions};
+pub use ordering::KeyOrdering;
+pub use pool::{PoolStats, PooledCursor, PooledTxn, ReadTxnPool};
+pub use retry::RetryPolicy;
+pub use snapshot::{OwnedIter, OwnedValue, Snapshot};
+pub use table::{Table, TableDef, TableIter};
+pub use transaction::{RoTxn, RwTxn, Txn};
+pub use value::Value;
+pub use version::{engine_version, EngineVersion};
+
 /// Database handle type
 pub type MDBX_dbi = u32;
 
@@ -51,6 +133,9 @@ pub type MDBX_env_flags_t = c_uint;
 /// Transaction flags type  
 pub type MDBX_txn_flags_t = c_uint;
 
+/// Key/data comparator function type
+pub type MDBX_cmp_func = unsafe extern "C" fn(a: *const MDBX_val, b: *const MDBX_val) -> c_int;
+
 /// Opaque environment handle
 #[repr(C)]
 pub struct MDBX_env {
@@ -128,7 +213,7 @@ pub struct MDBX_envinfo_bootid {
 
 /// Page operation statistics
 #[repr(C)]
-#[derive(Debug, Copy, Clone, Default)]
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
 pub struct MDBX_envinfo_pgop_stat {
     pub newly: u64,
     pub cow: u64,
@@ -197,8 +282,65 @@ pub struct MDBX_commit_latency {
     pub gc_cputime: u32,
 }
 
+/// Transaction information
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Default)]
+pub struct MDBX_txn_info {
+    pub txn_id: u64,
+    pub txn_reader_lag: u64,
+    pub txn_space_used: u64,
+    pub txn_space_limit_soft: u64,
+    pub txn_space_limit_hard: u64,
+    pub txn_space_retired: u64,
+    pub txn_space_leftover: u64,
+    pub txn_space_dirty: u64,
+}
+
+/// Git metadata of the engine build
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct MDBX_version_info_git {
+    pub datetime: *const c_char,
+    pub tree: *const c_char,
+    pub commit: *const c_char,
+    pub describe: *const c_char,
+}
+
+/// Engine version information
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct MDBX_version_info {
+    pub major: u16,
+    pub minor: u16,
+    pub patch: u16,
+    pub tweak: u16,
+    pub semver_prerelease: *const c_char,
+    pub git: MDBX_version_info_git,
+    pub sourcery: *const c_char,
+}
+
+/// Engine build information
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct MDBX_build_info {
+    pub datetime: *const c_char,
+    pub target: *const c_char,
+    pub options: *const c_char,
+    pub compiler: *const c_char,
+    pub flags: *const c_char,
+    pub metadata: *const c_char,
+}
+
 #[link(name = "mdbx_rs", kind = "static")]
 extern "C" {
+    // Version and build metadata
+
+    /// Version of the linked engine.
+    pub static mdbx_version: MDBX_version_info;
+
+    /// Build options of the linked engine.
+    pub static mdbx_build: MDBX_build_info;
+
     // Environment functions
 
     /// Create an MDBX environment handle.
@@ -287,6 +429,7 @@ extern "C" {
     pub fn mdbx_txn_env(txn: *const MDBX_txn) -> *mut MDBX_env;
     pub fn mdbx_txn_flags(txn: *const MDBX_txn) -> c_int;
     pub fn mdbx_txn_id(txn: *const MDBX_txn) -> u64;
+    pub fn mdbx_txn_info(txn: *const MDBX_txn, info: *mut MDBX_txn_info, scan_rlt: bool) -> c_int;
     pub fn mdbx_txn_reset(txn: *mut MDBX_txn) -> c_int;
     pub fn mdbx_txn_renew(txn: *mut MDBX_txn) -> c_int;
 
@@ -360,13 +503,58 @@ extern "C" {
     pub fn mdbx_cursor_count(cursor: *const MDBX_cursor, count: *mut usize) -> c_int;
 
     // Utility functions
+    /// Check whether `ptr` points into a dirty page of a write transaction.
+    ///
+    /// Returns `MDBX_RESULT_TRUE` if dirty, `MDBX_RESULT_FALSE` if not, or an error code.
+    pub fn mdbx_is_dirty(txn: *const MDBX_txn, ptr: *const c_void) -> c_int;
     pub fn mdbx_cmp(
         txn: *const MDBX_txn,
         dbi: MDBX_dbi,
         a: *const MDBX_val,
         b: *const MDBX_val,
     ) -> c_int;
+    /// Key comparator used by databases created with `flags`.
+    pub fn mdbx_get_keycmp(flags: c_uint) -> Option<MDBX_cmp_func>;
+    /// Data comparator used by `MDBX_DUPSORT` databases created with `flags`.
+    pub fn mdbx_get_datacmp(flags: c_uint) -> Option<MDBX_cmp_func>;
     pub fn mdbx_strerror(errnum: c_int) -> *const c_char;
+    /// Thread-safe variant of `mdbx_strerror` writing into `buf` if needed.
+    pub fn mdbx_strerror_r(errnum: c_int, buf: *mut c_char, buflen: usize) -> *const c_char;
+    /// Message for an MDBX-specific error code, or null for system errors.
+    pub fn mdbx_liberr2str(errnum: c_int) -> *const c_char;
+
+    // Key conversion functions
+
+    /// Order-preserving 64-bit key for a `double`.
+    pub fn mdbx_key_from_double(ieee754_64bit: f64) -> u64;
+    pub fn mdbx_key_from_ptrdouble(ieee754_64bit: *const f64) -> u64;
+    /// Order-preserving 32-bit key for a `float`.
+    pub fn mdbx_key_from_float(ieee754_32bit: f32) -> u32;
+    pub fn mdbx_key_from_ptrfloat(ieee754_32bit: *const f32) -> u32;
+    /// Key for an integer that sorts among the keys of `double` values,
+    /// exact within RFC 7159's range of ±(2^53 - 1).
+    pub fn mdbx_key_from_jsonInteger(json_integer: i64) -> u64;
+    pub fn mdbx_jsonInteger_from_key(val: MDBX_val) -> i64;
+    pub fn mdbx_double_from_key(val: MDBX_val) -> f64;
+    pub fn mdbx_float_from_key(val: MDBX_val) -> f32;
+    pub fn mdbx_int32_from_key(val: MDBX_val) -> i32;
+    pub fn mdbx_int64_from_key(val: MDBX_val) -> i64;
+}
+
+/// Order-preserving 64-bit key for a signed integer.
+///
+/// Inline in the C header, so not exported by the library.
+#[inline]
+pub const fn mdbx_key_from_int64(value: i64) -> u64 {
+    0x8000_0000_0000_0000u64.wrapping_add(value as u64)
+}
+
+/// Order-preserving 32-bit key for a signed integer.
+///
+/// Inline in the C header, so not exported by the library.
+#[inline]
+pub const fn mdbx_key_from_int32(value: i32) -> u32 {
+    0x8000_0000u32.wrapping_add(value as u32)
 }
 
 /// Convert a Rust byte slice to MDBX_val
@@ -386,6 +574,10 @@ pub fn bytes_to_val(bytes: &[u8]) -> MDBX_val {
 /// - `val.iov_base` points to valid memory if non-null
 /// - The memory is valid for `val.iov_len` bytes
 /// - The memory is not mutated during the lifetime `'a`
+///
+/// Values read inside a write transaction may point into dirty pages that
+/// move on the next `mdbx_put`; check with `mdbx_is_dirty` or use the safe
+/// [`Txn::get`] which copies such values.
 #[inline]
 pub unsafe fn val_to_bytes<'a>(val: &MDBX_val) -> &'a [u8] {
     if val.iov_base.is_null() || val.iov_len == 0 {
@@ -0,0 +1,393 @@
+//! OpenMetrics exporter for environment statistics.
+//!
+//! ```rust,ignore
+//! let metrics = MetricsCollector::new(&env)
+//!     .table(Some("users"))
+//!     .render()?;
+//! ```
+
+use std::fmt::{self, Write};
+
+use crate::constants::MDBX_NOTFOUND;
+use crate::environment::Environment;
+use crate::error::{Error, Result};
+use crate::info::{EnvInfo, Stat};
+use crate::latency::{CommitPhase, HistogramSnapshot};
+use crate::transaction::Txn;
+
+/// Label value used for the main (unnamed) database.
+pub const MAIN_TABLE_LABEL: &str = "@main";
+
+/// Collects statistics of an environment and a set of its tables.
+#[derive(Debug)]
+pub struct MetricsCollector<'env> {
+    env: &'env Environment,
+    tables: Vec<Option<String>>,
+}
+
+impl<'env> MetricsCollector<'env> {
+    /// Collector for the environment-wide metrics only.
+    pub fn new(env: &'env Environment) -> Self {
+        MetricsCollector {
+            env,
+            tables: Vec::new(),
+        }
+    }
+
+    /// Also report entries and pages of the table `name`, or of the main
+    /// database if `name` is `None`.
+    pub fn table(mut self, name: Option<&str>) -> Self {
+        self.tables.push(name.map(str::to_owned));
+        self
+    }
+
+    /// Read the current statistics.
+    ///
+    /// Tables that don't exist yet are skipped.
+    pub fn collect(&self) -> Result<MetricsSnapshot> {
+        let info = self.env.info()?;
+        let stat = self.env.stat()?;
+        let mut tables = Vec::with_capacity(self.tables.len());
+        if !self.tables.is_empty() {
+            let txn = self.env.begin_ro_txn()?;
+            for name in &self.tables {
+                let db = match txn.open_db(name.as_deref()) {
+                    Ok(db) => db,
+                    Err(Error::Mdbx(MDBX_NOTFOUND)) => continue,
+                    Err(err) => return Err(err),
+                };
+                let label = name.as_deref().unwrap_or(MAIN_TABLE_LABEL);
+                tables.push((label.to_owned(), db.stat(&txn)?));
+            }
+        }
+        let commit_latency = self
+            .env
+            .commit_latency_stats()
+            .map(|stats| stats.snapshot())
+            .unwrap_or_default();
+        Ok(MetricsSnapshot {
+            info,
+            stat,
+            tables,
+            commit_latency,
+            map_growths: self.env.map_growth_stats().map(|stats| stats.events()),
+        })
+    }
+
+    /// Collect and render in the OpenMetrics text format.
+    pub fn render(&self) -> Result<String> {
+        Ok(self.collect()?.to_string())
+    }
+}
+
+/// Statistics ready to be rendered as OpenMetrics text via `Display`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetricsSnapshot {
+    pub info: EnvInfo,
+    /// Statistics of the whole environment.
+    pub stat: Stat,
+    /// Statistics per table label.
+    pub tables: Vec<(String, Stat)>,
+    /// Commit-phase histograms, empty if latency stats are disabled.
+    pub commit_latency: Vec<(CommitPhase, HistogramSnapshot)>,
+    /// Map growths after `MDBX_MAP_FULL`, `None` without a growth policy.
+    pub map_growths: Option<u64>,
+}
+
+impl fmt::Display for MetricsSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let info = &self.info;
+        let geometry = &info.geometry;
+
+        header(f, "mdbx_geometry_bytes", "gauge", "Database size limits.")?;
+        for (bound, value) in [
+            ("lower", geometry.lower),
+            ("upper", geometry.upper),
+            ("current", geometry.current),
+            ("shrink", geometry.shrink),
+            ("grow", geometry.grow),
+        ] {
+            writeln!(f, "mdbx_geometry_bytes{{bound=\"{}\"}} {}", bound, value)?;
+        }
+        gauge(
+            f,
+            "mdbx_map_bytes",
+            "Size of the memory map.",
+            info.map_size,
+        )?;
+        gauge(
+            f,
+            "mdbx_file_bytes",
+            "Size of the database file.",
+            info.file_size,
+        )?;
+        gauge(
+            f,
+            "mdbx_used_bytes",
+            "Bytes used by allocated pages.",
+            info.used_bytes(),
+        )?;
+        gauge(
+            f,
+            "mdbx_page_size_bytes",
+            "Database page size.",
+            info.page_size as u64,
+        )?;
+        gauge(
+            f,
+            "mdbx_unsync_bytes",
+            "Bytes written since the last sync.",
+            info.unsync_volume,
+        )?;
+        gauge(
+            f,
+            "mdbx_recent_txnid",
+            "Id of the most recent committed transaction.",
+            info.recent_txnid,
+        )?;
+        gauge(
+            f,
+            "mdbx_reader_lag_txns",
+            "Transactions the oldest reader lags behind.",
+            info.reader_lag(),
+        )?;
+
+        header(f, "mdbx_readers", "gauge", "Reader slots.")?;
+        writeln!(f, "mdbx_readers{{state=\"active\"}} {}", info.num_readers)?;
+        writeln!(f, "mdbx_readers{{state=\"max\"}} {}", info.max_readers)?;
+
+        let pgop = &info.pgop_stat;
+        header(
+            f,
+            "mdbx_page_operations",
+            "counter",
+            "Page operations since the environment was opened.",
+        )?;
+        for (op, value) in [
+            ("newly", pgop.newly),
+            ("cow", pgop.cow),
+            ("clone", pgop.clone),
+            ("split", pgop.split),
+            ("merge", pgop.merge),
+            ("spill", pgop.spill),
+            ("unspill", pgop.unspill),
+            ("wops", pgop.wops),
+            ("prefault", pgop.prefault),
+            ("mincore", pgop.mincore),
+            ("msync", pgop.msync),
+            ("fsync", pgop.fsync),
+        ] {
+            writeln!(f, "mdbx_page_operations_total{{op=\"{}\"}} {}", op, value)?;
+        }
+
+        gauge(
+            f,
+            "mdbx_entries",
+            "Key/value pairs in the main database.",
+            self.stat.entries,
+        )?;
+        gauge(
+            f,
+            "mdbx_depth",
+            "Height of the main database B-tree.",
+            self.stat.depth as u64,
+        )?;
+
+        if !self.tables.is_empty() {
+            header(
+                f,
+                "mdbx_table_entries",
+                "gauge",
+                "Key/value pairs per table.",
+            )?;
+            for (table, stat) in &self.tables {
+                writeln!(
+                    f,
+                    "mdbx_table_entries{{table=\"{}\"}} {}",
+                    Escaped(table),
+                    stat.entries
+                )?;
+            }
+            header(f, "mdbx_table_pages", "gauge", "Pages per table.")?;
+            for (table, stat) in &self.tables {
+                for (kind, value) in [
+                    ("branch", stat.branch_pages),
+                    ("leaf", stat.leaf_pages),
+                    ("overflow", stat.overflow_pages),
+                ] {
+                    writeln!(
+                        f,
+                        "mdbx_table_pages{{table=\"{}\",type=\"{}\"}} {}",
+                        Escaped(table),
+                        kind,
+                        value
+                    )?;
+                }
+            }
+            header(
+                f,
+                "mdbx_table_depth",
+                "gauge",
+                "Height of the B-tree per table.",
+            )?;
+            for (table, stat) in &self.tables {
+                writeln!(
+                    f,
+                    "mdbx_table_depth{{table=\"{}\"}} {}",
+                    Escaped(table),
+                    stat.depth
+                )?;
+            }
+        }
+
+        if let Some(growths) = self.map_growths {
+            header(
+                f,
+                "mdbx_map_growths",
+                "counter",
+                "Upper bound raises after MDBX_MAP_FULL.",
+            )?;
+            writeln!(f, "mdbx_map_growths_total {}", growths)?;
+        }
+
+        if !self.commit_latency.is_empty() {
+            let name = "mdbx_commit_duration_seconds";
+            header(f, name, "histogram", "Time spent per commit phase.")?;
+            for (phase, histogram) in &self.commit_latency {
+                let phase = phase.name();
+                for (bound, count) in histogram.cumulative_buckets() {
+                    writeln!(
+                        f,
+                        "{}_bucket{{phase=\"{}\",le=\"{}\"}} {}",
+                        name,
+                        phase,
+                        bound.as_secs_f64(),
+                        count
+                    )?;
+                }
+                writeln!(
+                    f,
+                    "{}_bucket{{phase=\"{}\",le=\"+Inf\"}} {}",
+                    name,
+                    phase,
+                    histogram.count()
+                )?;
+                writeln!(
+                    f,
+                    "{}_sum{{phase=\"{}\"}} {}",
+                    name,
+                    phase,
+                    histogram.sum().as_secs_f64()
+                )?;
+                writeln!(
+                    f,
+                    "{}_count{{phase=\"{}\"}} {}",
+                    name,
+                    phase,
+                    histogram.count()
+                )?;
+            }
+        }
+
+        writeln!(f, "# EOF")
+    }
+}
+
+fn header(f: &mut impl Write, name: &str, kind: &str, help: &str) -> fmt::Result {
+    writeln!(f, "# TYPE {} {}", name, kind)?;
+    writeln!(f, "# HELP {} {}", name, help)
+}
+
+fn gauge(f: &mut impl Write, name: &str, help: &str, value: u64) -> fmt::Result {
+    header(f, name, "gauge", help)?;
+    writeln!(f, "{} {}", name, value)
+}
+
+/// Label value with `\`, `"` and newlines escaped.
+struct Escaped<'a>(&'a str);
+
+impl fmt::Display for Escaped<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for c in self.0.chars() {
+            match c {
+                '\\' => f.write_str("\\\\")?,
+                '"' => f.write_str("\\\"")?,
+                '\n' => f.write_str("\\n")?,
+                c => f.write_char(c)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::latency::CommitLatency;
+    use crate::latency::CommitLatencyStats;
+    use std::time::Duration;
+
+    fn snapshot() -> MetricsSnapshot {
+        let mut info = EnvInfo::default();
+        info.geometry.upper = 1 << 30;
+        info.num_readers = 3;
+        info.max_readers = 126;
+        info.pgop_stat.split = 7;
+        let latency = CommitLatencyStats::default();
+        latency.record(&CommitLatency {
+            whole: Duration::from_micros(1500),
+            ..Default::default()
+        });
+        MetricsSnapshot {
+            info,
+            stat: Stat {
+                entries: 2,
+                ..Default::default()
+            },
+            tables: vec![(
+                "us\"ers".to_owned(),
+                Stat {
+                    entries: 42,
+                    leaf_pages: 5,
+                    ..Default::default()
+                },
+            )],
+            commit_latency: latency.snapshot(),
+            map_growths: Some(2),
+        }
+    }
+
+    #[test]
+    fn test_render_openmetrics() {
+        let text = snapshot().to_string();
+        assert!(text.ends_with("# EOF\n"));
+        assert!(text.contains("# TYPE mdbx_geometry_bytes gauge\n"));
+        assert!(text.contains("mdbx_geometry_bytes{bound=\"upper\"} 1073741824\n"));
+        assert!(text.contains("mdbx_readers{state=\"active\"} 3\n"));
+        assert!(text.contains("mdbx_page_operations_total{op=\"split\"} 7\n"));
+        assert!(text.contains("mdbx_entries 2\n"));
+        assert!(text.contains("# TYPE mdbx_map_growths counter\n"));
+        assert!(text.contains("mdbx_map_growths_total 2\n"));
+        assert!(text.contains("mdbx_table_entries{table=\"us\\\"ers\"} 42\n"));
+        assert!(text.contains("mdbx_table_pages{table=\"us\\\"ers\",type=\"leaf\"} 5\n"));
+        assert!(
+            text.contains("mdbx_commit_duration_seconds_bucket{phase=\"whole\",le=\"+Inf\"} 1\n")
+        );
+        assert!(text.contains("mdbx_commit_duration_seconds_sum{phase=\"whole\"} 0.0015\n"));
+        assert!(text.contains("mdbx_commit_duration_seconds_count{phase=\"sync\"} 1\n"));
+        assert!(text.contains("mdbx_commit_duration_seconds_bucket{phase=\"sync\",le=\"0\"} 1\n"));
+    }
+
+    #[test]
+    fn test_render_without_optional_sections() {
+        let mut snapshot = snapshot();
+        snapshot.tables.clear();
+        snapshot.commit_latency.clear();
+        snapshot.map_growths = None;
+        let text = snapshot.to_string();
+        assert!(!text.contains("mdbx_map_growths"));
+        assert!(!text.contains("mdbx_table_entries"));
+        assert!(!text.contains("mdbx_commit_duration_seconds"));
+        assert_eq!(text.matches("# EOF").count(), 1);
+    }
+}
@@ -0,0 +1,358 @@
+//! Multimap view over `MDBX_DUPSORT` tables.
+
+use std::iter::FusedIterator;
+use std::ops::{Bound, RangeBounds};
+
+use crate::constants::MDBX_KEYEXIST;
+use crate::cursor::Cursor;
+use crate::database::Database;
+use crate::error::{mdbx_result, Error, Result};
+use crate::flags::{DbFlags, PutFlags};
+use crate::iter::KeyRange;
+use crate::ordering::KeyOrdering;
+use crate::transaction::{RwTxn, Txn};
+use crate::value::Value;
+use crate::MDBX_cursor_op::*;
+use crate::{mdbx_cursor_count, mdbx_cursor_del};
+
+/// A table mapping each key to a sorted set of values.
+///
+/// ```rust,ignore
+/// let tags = MultiMap::new(&txn, txn.create_db(Some("tags"), DbFlags::DUPSORT)?)?;
+/// tags.insert(b"post:1", b"rust")?;
+/// assert!(!tags.insert_unique(b"post:1", b"rust")?);
+/// assert_eq!(tags.count(b"post:1")?, 1);
+/// ```
+#[derive(Debug)]
+pub struct MultiMap<'txn, T: Txn + ?Sized> {
+    txn: &'txn T,
+    db: Database,
+    data_cmp: KeyOrdering,
+}
+
+impl<'txn, T: Txn + ?Sized> MultiMap<'txn, T> {
+    /// View `db`, which must have been created with `MDBX_DUPSORT`.
+    pub fn new(txn: &'txn T, db: Database) -> Result<Self> {
+        check_dupsort(db)?;
+        Ok(MultiMap {
+            txn,
+            db,
+            data_cmp: KeyOrdering::for_dup_flags(db.flags())?,
+        })
+    }
+
+    /// The underlying database.
+    #[inline]
+    pub fn db(&self) -> Database {
+        self.db
+    }
+
+    /// All values stored under `key`, in table order.
+    pub fn get_all(&self, key: &[u8]) -> Result<Vec<Value<'txn>>> {
+        self.iter_dups(key)?.collect()
+    }
+
+    /// Iterate over the distinct keys.
+    pub fn iter_keys(&self) -> Result<Keys<'txn, T>> {
+        Ok(Keys {
+            cursor: Cursor::open(self.txn, self.db)?,
+            started: false,
+            done: false,
+        })
+    }
+
+    /// Iterate over the values stored under `key`.
+    pub fn iter_dups(&self, key: &[u8]) -> Result<Dups<'txn, T>> {
+        self.range_dups(key, ..)
+    }
+
+    /// Iterate over the values stored under `key` that fall within `range`.
+    pub fn range_dups<'d, R: RangeBounds<&'d [u8]>>(
+        &self,
+        key: &[u8],
+        range: R,
+    ) -> Result<Dups<'txn, T>> {
+        let range = KeyRange::new(&range);
+        range.check(&self.data_cmp)?;
+        Ok(Dups {
+            cursor: Cursor::open(self.txn, self.db)?,
+            key: key.to_vec(),
+            range,
+            data_cmp: self.data_cmp,
+            started: false,
+            done: false,
+        })
+    }
+
+    /// Number of values stored under `key`.
+    pub fn count(&self, key: &[u8]) -> Result<usize> {
+        let mut cursor = Cursor::open(self.txn, self.db)?;
+        if cursor.set(key)?.is_none() {
+            return Ok(0);
+        }
+        let mut count = 0;
+        mdbx_result(unsafe { mdbx_cursor_count(cursor.as_ptr(), &mut count) })?;
+        Ok(count)
+    }
+}
+
+impl<'txn, 'env> MultiMap<'txn, RwTxn<'env>> {
+    /// Add `data` to the values of `key`; adding an existing pair is a no-op.
+    pub fn insert(&self, key: &[u8], data: &[u8]) -> Result<()> {
+        self.txn.put(self.db, key, data, PutFlags::empty())
+    }
+
+    /// Add `data` to the values of `key` with `MDBX_NODUPDATA`.
+    ///
+    /// Returns `false` if the pair already existed.
+    pub fn insert_unique(&self, key: &[u8], data: &[u8]) -> Result<bool> {
+        match self.txn.put(self.db, key, data, PutFlags::NODUPDATA) {
+            Ok(()) => Ok(true),
+            Err(Error::Mdbx(MDBX_KEYEXIST)) => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Remove one `key`/`data` pair.
+    ///
+    /// Returns `false` if the pair didn't exist.
+    pub fn remove(&self, key: &[u8], data: &[u8]) -> Result<bool> {
+        self.txn.del(self.db, key, Some(data))
+    }
+
+    /// Remove `key` with all its values using `MDBX_ALLDUPS`.
+    ///
+    /// Returns `false` if the key didn't exist.
+    pub fn remove_all(&self, key: &[u8]) -> Result<bool> {
+        let mut cursor = Cursor::open(self.txn, self.db)?;
+        if cursor.set(key)?.is_none() {
+            return Ok(false);
+        }
+        mdbx_result(unsafe { mdbx_cursor_del(cursor.as_ptr(), PutFlags::ALLDUPS.bits()) })?;
+        let db = self.db;
+        self.txn.record(|changes| changes.key(db, key));
+        Ok(true)
+    }
+}
+
+fn check_dupsort(db: Database) -> Result<()> {
+    if !db.flags().contains(DbFlags::DUPSORT) {
+        return Err(Error::InvalidFlags(
+            "MultiMap requires an MDBX_DUPSORT database",
+        ));
+    }
+    Ok(())
+}
+
+/// Iterator over the distinct keys of a [`MultiMap`].
+#[derive(Debug)]
+pub struct Keys<'txn, T: Txn + ?Sized> {
+    cursor: Cursor<'txn, T>,
+    started: bool,
+    done: bool,
+}
+
+impl<'txn, T: Txn + ?Sized> Iterator for Keys<'txn, T> {
+    type Item = Result<Value<'txn>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let op = if self.started {
+            MDBX_NEXT_NODUP
+        } else {
+            MDBX_FIRST
+        };
+        self.started = true;
+        match self.cursor.get(op, None, None) {
+            Ok(Some((key, _))) => Some(Ok(key)),
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            Err(err) => {
+                self.done = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+impl<T: Txn + ?Sized> FusedIterator for Keys<'_, T> {}
+
+/// Iterator over the values of one key of a [`MultiMap`].
+#[derive(Debug)]
+pub struct Dups<'txn, T: Txn + ?Sized> {
+    cursor: Cursor<'txn, T>,
+    key: Vec<u8>,
+    range: KeyRange,
+    data_cmp: KeyOrdering,
+    started: bool,
+    done: bool,
+}
+
+impl<'txn, T: Txn + ?Sized> Dups<'txn, T> {
+    fn next_dup(&mut self) -> Result<Option<Value<'txn>>> {
+        let item = if self.started {
+            self.cursor.get(MDBX_NEXT_DUP, None, None)?
+        } else {
+            self.started = true;
+            self.seek()?
+        };
+        Ok(item
+            .map(|(_, data)| data)
+            .filter(|data| self.range.before_end(data, self.data_cmp.order_fn())))
+    }
+
+    /// Position at the first value of the key within the range.
+    fn seek(&mut self) -> Result<Option<(Value<'txn>, Value<'txn>)>> {
+        let key = Some(self.key.as_slice());
+        let (data, exclusive) = match self.range.start() {
+            Bound::Unbounded => return self.cursor.get(MDBX_SET_KEY, key, None),
+            Bound::Included(data) => (data, false),
+            Bound::Excluded(data) => (data, true),
+        };
+        let item = self.cursor.get(MDBX_GET_BOTH_RANGE, key, Some(data))?;
+        match item {
+            Some((_, found)) if exclusive && self.data_cmp.order_fn()(&found, data).is_eq() => {
+                self.cursor.get(MDBX_NEXT_DUP, None, None)
+            }
+            item => Ok(item),
+        }
+    }
+}
+
+impl<'txn, T: Txn + ?Sized> Iterator for Dups<'txn, T> {
+    type Item = Result<Value<'txn>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        match self.next_dup() {
+            Ok(Some(data)) => Some(Ok(data)),
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            Err(err) => {
+                self.done = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+impl<T: Txn + ?Sized> FusedIterator for Dups<'_, T> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TestEnv;
+
+    #[test]
+    fn test_check_dupsort() {
+        assert!(check_dupsort(Database::new(1, DbFlags::DUPSORT)).is_ok());
+        assert!(check_dupsort(Database::new(1, DbFlags::DUPSORT | DbFlags::DUPFIXED)).is_ok());
+        assert_eq!(
+            check_dupsort(Database::new(1, DbFlags::empty())),
+            Err(Error::InvalidFlags(
+                "MultiMap requires an MDBX_DUPSORT database"
+            ))
+        );
+    }
+
+    fn collect<'txn>(iter: impl Iterator<Item = Result<Value<'txn>>>) -> Vec<Vec<u8>> {
+        iter.map(|value| value.unwrap().to_vec()).collect()
+    }
+
+    fn fill(txn: &RwTxn<'_>) -> Database {
+        let db = txn.create_db(Some("tags"), DbFlags::DUPSORT).unwrap();
+        let map = MultiMap::new(txn, db).unwrap();
+        for (key, data) in [("a", "1"), ("b", "1"), ("b", "2"), ("b", "3"), ("c", "1")] {
+            map.insert(key.as_bytes(), data.as_bytes()).unwrap();
+        }
+        db
+    }
+
+    #[test]
+    fn test_insert_and_count() {
+        let env = TestEnv::new();
+        let txn = env.begin_rw_txn().unwrap();
+        let map = MultiMap::new(&txn, fill(&txn)).unwrap();
+
+        assert!(!map.insert_unique(b"b", b"2").unwrap());
+        assert!(map.insert_unique(b"b", b"4").unwrap());
+        map.insert(b"b", b"4").unwrap();
+        assert_eq!(map.count(b"b").unwrap(), 4);
+        assert_eq!(map.count(b"a").unwrap(), 1);
+        assert_eq!(map.count(b"missing").unwrap(), 0);
+        let all: Vec<_> = map
+            .get_all(b"b")
+            .unwrap()
+            .iter()
+            .map(|v| v.to_vec())
+            .collect();
+        assert_eq!(all, [b"1", b"2", b"3", b"4"]);
+
+        assert!(map.remove(b"b", b"4").unwrap());
+        assert!(!map.remove(b"b", b"4").unwrap());
+        assert_eq!(map.count(b"b").unwrap(), 3);
+    }
+
+    #[test]
+    fn test_iter_keys() {
+        let env = TestEnv::new();
+        let txn = env.begin_rw_txn().unwrap();
+        let map = MultiMap::new(&txn, fill(&txn)).unwrap();
+        // Each key once, however many values it has
+        assert_eq!(collect(map.iter_keys().unwrap()), [b"a", b"b", b"c"]);
+
+        let empty = txn.create_db(Some("empty"), DbFlags::DUPSORT).unwrap();
+        let map = MultiMap::new(&txn, empty).unwrap();
+        assert_eq!(map.iter_keys().unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_range_dups() {
+        let env = TestEnv::new();
+        let txn = env.begin_rw_txn().unwrap();
+        let map = MultiMap::new(&txn, fill(&txn)).unwrap();
+        let (one, two, three): (&[u8], &[u8], &[u8]) = (b"1", b"2", b"3");
+
+        assert_eq!(collect(map.iter_dups(b"b").unwrap()), [b"1", b"2", b"3"]);
+        assert_eq!(collect(map.range_dups(b"b", two..).unwrap()), [b"2", b"3"]);
+        assert_eq!(collect(map.range_dups(b"b", ..two).unwrap()), [b"1"]);
+        assert_eq!(collect(map.range_dups(b"b", ..=two).unwrap()), [b"1", b"2"]);
+        assert_eq!(
+            collect(
+                map.range_dups(b"b", (Bound::Excluded(one), Bound::Excluded(three)))
+                    .unwrap()
+            ),
+            [b"2"]
+        );
+        assert_eq!(
+            collect(
+                map.range_dups(b"b", (Bound::Excluded(three), Bound::Unbounded))
+                    .unwrap()
+            ),
+            Vec::<Vec<u8>>::new()
+        );
+        // Values of the neighbouring keys are never included
+        assert_eq!(collect(map.range_dups(b"a", one..).unwrap()), [b"1"]);
+        assert_eq!(map.iter_dups(b"missing").unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_remove_all() {
+        let env = TestEnv::new();
+        let txn = env.begin_rw_txn().unwrap();
+        let map = MultiMap::new(&txn, fill(&txn)).unwrap();
+
+        assert!(map.remove_all(b"b").unwrap());
+        assert!(!map.remove_all(b"b").unwrap());
+        assert_eq!(map.count(b"b").unwrap(), 0);
+        assert_eq!(collect(map.iter_keys().unwrap()), [b"a", b"c"]);
+    }
+}
@@ -0,0 +1,374 @@
+//! Typed runtime options over `mdbx_env_set_option` / `mdbx_env_get_option`.
+//!
+//! Each `MDBX_opt_*` option is exposed as an [`EnvOption`] variant carrying a
+//! value in natural units, and as a marker type in [`opt`] for typed reads
+//! with [`Environment::get`](crate::Environment::get).
+
+#![allow(non_upper_case_globals)]
+
+use std::fmt;
+use std::os::raw::c_int;
+use std::time::Duration;
+
+use crate::constants::*;
+use crate::error::{Error, Result};
+
+/// Conversions between option values and the engine's raw `u64`.
+mod codec {
+    use super::*;
+
+    pub mod count {
+        use super::*;
+
+        pub fn encode(value: u64) -> Result<u64> {
+            Ok(value)
+        }
+
+        pub fn decode(raw: u64) -> u64 {
+            raw
+        }
+    }
+
+    pub mod flag {
+        use super::*;
+
+        pub fn encode(value: bool) -> Result<u64> {
+            Ok(value as u64)
+        }
+
+        pub fn decode(raw: u64) -> bool {
+            raw != 0
+        }
+    }
+
+    pub mod denominator {
+        use super::*;
+
+        pub fn encode(value: u8) -> Result<u64> {
+            Ok(value as u64)
+        }
+
+        pub fn decode(raw: u64) -> u8 {
+            raw.min(u8::MAX as u64) as u8
+        }
+    }
+
+    /// Durations in 16.16 fixed-point seconds.
+    pub mod seconds16 {
+        use super::*;
+
+        pub fn encode(value: Duration) -> Result<u64> {
+            let raw = (value.as_secs_f64() * 65536.0).round();
+            if raw > u32::MAX as f64 {
+                return Err(Error::InvalidOption("duration exceeds 65535 seconds"));
+            }
+            Ok(raw as u64)
+        }
+
+        pub fn decode(raw: u64) -> Duration {
+            duration_from_16dot16(raw)
+        }
+    }
+
+    /// Percentages in 16.16 fixed point, limited by the engine to 12.5..=50%.
+    pub mod percent16 {
+        use super::*;
+
+        pub fn encode(value: f64) -> Result<u64> {
+            if !(12.5..=50.0).contains(&value) {
+                return Err(Error::InvalidOption("percentage must be within 12.5..=50"));
+            }
+            Ok((value * 65536.0 / 100.0).round() as u64)
+        }
+
+        pub fn decode(raw: u64) -> f64 {
+            raw as f64 * 100.0 / 65536.0
+        }
+    }
+
+    /// Fractions of a page in 1/65535 units.
+    pub mod fraction16 {
+        use super::*;
+
+        pub fn encode(value: f64) -> Result<u64> {
+            if !(0.0..=1.0).contains(&value) {
+                return Err(Error::InvalidOption("fraction must be within 0..=1"));
+            }
+            Ok((value * 65535.0).round() as u64)
+        }
+
+        pub fn decode(raw: u64) -> f64 {
+            raw as f64 / 65535.0
+        }
+    }
+}
+
+/// Decode a 16.16 fixed-point number of seconds.
+pub(crate) fn duration_from_16dot16(raw: u64) -> Duration {
+    Duration::from_secs_f64(raw as f64 / 65536.0)
+}
+
+/// An option whose value can be read with a typed result.
+pub trait EnvOptionKind {
+    /// Raw `MDBX_opt_*` identifier.
+    const ID: c_int;
+    /// Value type in natural units.
+    type Value;
+
+    /// Decode the raw value returned by `mdbx_env_get_option`.
+    fn decode(raw: u64) -> Self::Value;
+}
+
+macro_rules! env_options {
+    ($(
+        $(#[$doc:meta])*
+        $name:ident($ty:ty) = $id:ident, $label:literal, $codec:ident;
+    )*) => {
+        /// A runtime option together with its value.
+        #[derive(Debug, Clone, Copy, PartialEq)]
+        #[non_exhaustive]
+        pub enum EnvOption {
+            $($(#[$doc])* $name($ty),)*
+        }
+
+        impl EnvOption {
+            /// Every option identifier, in `MDBX_opt_*` order.
+            pub(crate) const ALL: &'static [c_int] = &[$($id),*];
+
+            /// Raw `MDBX_opt_*` identifier.
+            pub fn id(&self) -> c_int {
+                match self {
+                    $(EnvOption::$name(_) => $id,)*
+                }
+            }
+
+            /// Option name as used in the engine documentation.
+            pub fn name(&self) -> &'static str {
+                match self {
+                    $(EnvOption::$name(_) => $label,)*
+                }
+            }
+
+            /// Validate and encode the value for `mdbx_env_set_option`.
+            pub(crate) fn encode(&self) -> Result<u64> {
+                match *self {
+                    $(EnvOption::$name(value) => codec::$codec::encode(value),)*
+                }
+            }
+
+            /// Decode a raw value returned by `mdbx_env_get_option`.
+            pub(crate) fn decode(id: c_int, raw: u64) -> Option<Self> {
+                match id {
+                    $($id => Some(EnvOption::$name(codec::$codec::decode(raw))),)*
+                    _ => None,
+                }
+            }
+        }
+
+        /// Marker types for typed reads of each option.
+        pub mod opt {
+            use super::*;
+
+            $(
+                $(#[$doc])*
+                #[derive(Debug, Clone, Copy)]
+                pub struct $name;
+
+                impl EnvOptionKind for $name {
+                    const ID: c_int = $id;
+                    type Value = $ty;
+
+                    fn decode(raw: u64) -> $ty {
+                        codec::$codec::decode(raw)
+                    }
+                }
+            )*
+        }
+    };
+}
+
+env_options! {
+    /// Maximum number of named databases.
+    MaxDb(u64) = MDBX_opt_max_db, "max_db", count;
+    /// Maximum number of reader slots.
+    MaxReaders(u64) = MDBX_opt_max_readers, "max_readers", count;
+    /// Unsynced bytes after which a commit syncs.
+    SyncBytes(u64) = MDBX_opt_sync_bytes, "sync_bytes", count;
+    /// Time after which a commit syncs.
+    SyncPeriod(Duration) = MDBX_opt_sync_period, "sync_period", seconds16;
+    /// Pages to read from the GC before allocating new ones.
+    RpAugmentLimit(u64) = MDBX_opt_rp_augment_limit, "rp_augment_limit", count;
+    /// Loose pages kept before returning them to the GC.
+    LooseLimit(u64) = MDBX_opt_loose_limit, "loose_limit", count;
+    /// Pages kept in the dirty page reserve.
+    DpReserveLimit(u64) = MDBX_opt_dp_reserve_limit, "dp_reserve_limit", count;
+    /// Dirty pages allowed per write transaction before spilling.
+    TxnDpLimit(u64) = MDBX_opt_txn_dp_limit, "txn_dp_limit", count;
+    /// Initial capacity of the dirty page list.
+    TxnDpInitial(u64) = MDBX_opt_txn_dp_initial, "txn_dp_initial", count;
+    /// Maximum fraction of dirty pages spilled at once (1/N).
+    SpillMaxDenominator(u8) = MDBX_opt_spill_max_denominator, "spill_max_denominator", denominator;
+    /// Minimum fraction of dirty pages spilled at once (1/N).
+    SpillMinDenominator(u8) = MDBX_opt_spill_min_denominator, "spill_min_denominator", denominator;
+    /// Fraction of the parent's dirty pages spilled for a nested transaction (1/N).
+    SpillParent4ChildDenominator(u8) =
+        MDBX_opt_spill_parent4child_denominator, "spill_parent4child_denominator", denominator;
+    /// Page fill percentage below which pages are merged (12.5..=50).
+    MergeThresholdPercent(f64) =
+        MDBX_opt_merge_threshold_16dot16_percent, "merge_threshold_16dot16_percent", percent16;
+    /// Dirty pages above which commits write through instead of `msync`.
+    WritethroughThreshold(u64) = MDBX_opt_writethrough_threshold, "writethrough_threshold", count;
+    /// Prefault pages before writing to them.
+    PrefaultWriteEnable(bool) = MDBX_opt_prefault_write_enable, "prefault_write_enable", flag;
+    /// Time limit for GC reclaiming during a commit.
+    GcTimeLimit(Duration) = MDBX_opt_gc_time_limit, "gc_time_limit", seconds16;
+    /// Prefer fewer writes over balanced pages on splits.
+    PreferWafInsteadofBalance(bool) =
+        MDBX_opt_prefer_waf_insteadof_balance, "prefer_waf_insteadof_balance", flag;
+    /// Maximum size of a nested dup sub-page, as a fraction of the page.
+    SubpageLimit(f64) = MDBX_opt_subpage_limit, "subpage_limit", fraction16;
+    /// Free room in a page below which sub-pages are moved out, as a fraction.
+    SubpageRoomThreshold(f64) = MDBX_opt_subpage_room_threshold, "subpage_room_threshold", fraction16;
+    /// Free room required to reserve space in sub-pages, as a fraction.
+    SubpageReservePrereq(f64) = MDBX_opt_subpage_reserve_prereq, "subpage_reserve_prereq", fraction16;
+    /// Maximum space reserved in sub-pages, as a fraction.
+    SubpageReserveLimit(f64) = MDBX_opt_subpage_reserve_limit, "subpage_reserve_limit", fraction16;
+}
+
+impl EnvOption {
+    /// Whether the engine keeps the value in the lock file, so it can only
+    /// be set once the environment is open.
+    pub(crate) fn needs_open_env(&self) -> bool {
+        matches!(self, EnvOption::SyncBytes(_) | EnvOption::SyncPeriod(_))
+    }
+
+    /// Validate range limits the engine would otherwise reject.
+    pub fn validate(&self) -> Result<()> {
+        match *self {
+            EnvOption::MaxDb(dbs) if dbs > MDBX_MAX_DBI as u64 => {
+                Err(Error::InvalidOption("max_db exceeds MDBX_MAX_DBI"))
+            }
+            EnvOption::MaxReaders(0) => Err(Error::InvalidOption("max_readers must be positive")),
+            EnvOption::LooseLimit(limit) if limit > 255 => {
+                Err(Error::InvalidOption("loose_limit must be at most 255"))
+            }
+            _ => self.encode().map(|_| ()),
+        }
+    }
+}
+
+impl fmt::Display for EnvOption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}=", self.name())?;
+        match self {
+            EnvOption::SyncPeriod(value) | EnvOption::GcTimeLimit(value) => {
+                write!(f, "{:?}", value)
+            }
+            EnvOption::MergeThresholdPercent(value) => write!(f, "{:.2}%", value),
+            EnvOption::SubpageLimit(value)
+            | EnvOption::SubpageRoomThreshold(value)
+            | EnvOption::SubpageReservePrereq(value)
+            | EnvOption::SubpageReserveLimit(value) => write!(f, "{:.4}", value),
+            EnvOption::PrefaultWriteEnable(value) | EnvOption::PreferWafInsteadofBalance(value) => {
+                write!(f, "{}", value)
+            }
+            EnvOption::SpillMaxDenominator(value)
+            | EnvOption::SpillMinDenominator(value)
+            | EnvOption::SpillParent4ChildDenominator(value) => write!(f, "{}", value),
+            EnvOption::MaxDb(value)
+            | EnvOption::MaxReaders(value)
+            | EnvOption::SyncBytes(value)
+            | EnvOption::RpAugmentLimit(value)
+            | EnvOption::LooseLimit(value)
+            | EnvOption::DpReserveLimit(value)
+            | EnvOption::TxnDpLimit(value)
+            | EnvOption::TxnDpInitial(value)
+            | EnvOption::WritethroughThreshold(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+/// Snapshot of all runtime options, for diagnostics dumps.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnvOptions(pub Vec<EnvOption>);
+
+impl EnvOptions {
+    /// The value of a single option, if it was captured.
+    pub fn get<O: EnvOptionKind>(&self) -> Option<O::Value> {
+        let option = self.0.iter().find(|option| option.id() == O::ID)?;
+        let raw = option.encode().ok()?;
+        Some(O::decode(raw))
+    }
+}
+
+impl fmt::Display for EnvOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for option in &self.0 {
+            writeln!(f, "{}", option)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_option_ids() {
+        assert_eq!(EnvOption::ALL.len(), 21);
+        for (index, &id) in EnvOption::ALL.iter().enumerate() {
+            assert_eq!(id, index as c_int);
+            let option = EnvOption::decode(id, 0).unwrap();
+            assert_eq!(option.id(), id);
+        }
+        assert_eq!(EnvOption::decode(21, 0), None);
+        assert_eq!(opt::GcTimeLimit::ID, MDBX_opt_gc_time_limit);
+    }
+
+    #[test]
+    fn test_seconds16_roundtrip() {
+        let option = EnvOption::SyncPeriod(Duration::from_millis(1500));
+        assert_eq!(option.encode(), Ok(98304));
+        assert_eq!(opt::SyncPeriod::decode(98304), Duration::from_millis(1500));
+        assert!(EnvOption::GcTimeLimit(Duration::from_secs(70_000))
+            .validate()
+            .is_err());
+    }
+
+    #[test]
+    fn test_percent_and_fraction() {
+        assert_eq!(EnvOption::MergeThresholdPercent(25.0).encode(), Ok(16384));
+        assert_eq!(opt::MergeThresholdPercent::decode(16384), 25.0);
+        assert!(EnvOption::MergeThresholdPercent(60.0).validate().is_err());
+        assert_eq!(EnvOption::SubpageLimit(1.0).encode(), Ok(65535));
+        assert!(EnvOption::SubpageLimit(1.5).validate().is_err());
+    }
+
+    #[test]
+    fn test_option_validation() {
+        assert!(EnvOption::MaxDb(16).validate().is_ok());
+        assert!(EnvOption::MaxDb(100_000).validate().is_err());
+        assert!(EnvOption::MaxReaders(0).validate().is_err());
+        assert!(EnvOption::LooseLimit(256).validate().is_err());
+    }
+
+    #[test]
+    fn test_snapshot() {
+        let snapshot = EnvOptions(vec![
+            EnvOption::MaxDb(8),
+            EnvOption::SyncPeriod(Duration::from_secs(2)),
+            EnvOption::PrefaultWriteEnable(true),
+        ]);
+        assert_eq!(snapshot.get::<opt::MaxDb>(), Some(8));
+        assert_eq!(
+            snapshot.get::<opt::SyncPeriod>(),
+            Some(Duration::from_secs(2))
+        );
+        assert_eq!(snapshot.get::<opt::LooseLimit>(), None);
+        assert_eq!(
+            snapshot.to_string(),
+            "max_db=8\nsync_period=2s\nprefault_write_enable=true\n"
+        );
+    }
+}
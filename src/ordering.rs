@@ -0,0 +1,216 @@
+//! Engine comparators usable without a transaction.
+
+use std::cmp::Ordering;
+use std::fmt;
+
+use crate::constants::{MDBX_BAD_VALSIZE, MDBX_EINVAL};
+use crate::database::Database;
+use crate::error::{Error, Result};
+use crate::flags::DbFlags;
+use crate::*;
+
+/// The comparator the engine uses for a given set of database flags.
+///
+/// Unlike `mdbx_cmp` this needs no transaction or database handle, so it can
+/// be used to sort or merge keys in memory (e.g. results from several shards)
+/// in exactly the order an `MDBX_REVERSEKEY`, `MDBX_INTEGERKEY` or
+/// `MDBX_REVERSEDUP` table keeps them on disk.
+///
+/// Integer keys must be 4 or 8 bytes long; other lengths fail with
+/// `MDBX_BAD_VALSIZE` instead of reaching the engine's comparator.
+#[derive(Clone, Copy)]
+pub struct KeyOrdering {
+    cmp: MDBX_cmp_func,
+    integer: bool,
+}
+
+impl KeyOrdering {
+    /// Key comparator for a database created with `db_flags`.
+    pub fn for_flags(db_flags: DbFlags) -> Result<Self> {
+        let cmp = unsafe { mdbx_get_keycmp(db_flags.bits()) };
+        cmp.map(|cmp| KeyOrdering {
+            cmp,
+            integer: db_flags.contains(DbFlags::INTEGERKEY),
+        })
+        .ok_or(Error::from_code(MDBX_EINVAL))
+    }
+
+    /// Data comparator for an `MDBX_DUPSORT` database created with `db_flags`.
+    pub fn for_dup_flags(db_flags: DbFlags) -> Result<Self> {
+        let cmp = unsafe { mdbx_get_datacmp(db_flags.bits()) };
+        cmp.map(|cmp| KeyOrdering {
+            cmp,
+            integer: db_flags.contains(DbFlags::INTEGERDUP),
+        })
+        .ok_or(Error::from_code(MDBX_EINVAL))
+    }
+
+    /// Key comparator for `db`.
+    pub fn for_db(db: Database) -> Result<Self> {
+        Self::for_flags(db.flags())
+    }
+
+    /// Check that `key` can be compared, i.e. has 4 or 8 bytes for integer
+    /// orderings.
+    pub fn check(&self, key: &[u8]) -> Result<()> {
+        if self.integer && integer(key).is_none() {
+            return Err(Error::from_code(MDBX_BAD_VALSIZE));
+        }
+        Ok(())
+    }
+
+    /// Compare two keys.
+    ///
+    /// Integer keys must both have 4 or both have 8 bytes.
+    pub fn compare(&self, a: &[u8], b: &[u8]) -> Result<Ordering> {
+        if self.integer {
+            self.check(a)?;
+            self.check(b)?;
+            if a.len() != b.len() {
+                return Err(Error::from_code(MDBX_BAD_VALSIZE));
+            }
+        }
+        Ok(self.order(a, b))
+    }
+
+    /// [`compare`] as a closure.
+    ///
+    /// For `sort_by` and other APIs expecting a plain [`Ordering`], use
+    /// [`order_fn`] instead.
+    ///
+    /// [`compare`]: KeyOrdering::compare
+    /// [`order_fn`]: KeyOrdering::order_fn
+    pub fn as_fn(&self) -> impl Fn(&[u8], &[u8]) -> Result<Ordering> + Copy {
+        let ordering = *self;
+        move |a, b| ordering.compare(a, b)
+    }
+
+    /// Sort `keys` in database order.
+    ///
+    /// Fails without sorting if integer keys differ in length.
+    pub fn sort<T: AsRef<[u8]>>(&self, keys: &mut [T]) -> Result<()> {
+        if self.integer {
+            let len = keys.first().map_or(0, |key| key.as_ref().len());
+            for key in keys.iter() {
+                let key = key.as_ref();
+                self.check(key)?;
+                if key.len() != len {
+                    return Err(Error::from_code(MDBX_BAD_VALSIZE));
+                }
+            }
+        }
+        keys.sort_by(|a, b| self.order(a.as_ref(), b.as_ref()));
+        Ok(())
+    }
+
+    /// Infallible comparator, e.g. for `sort_by`.
+    ///
+    /// Keys must have been read from the table or passed [`check`], and
+    /// integer keys must all have the same length. Other keys still compare
+    /// consistently, but not in database order.
+    ///
+    /// [`check`]: KeyOrdering::check
+    pub fn order_fn(&self) -> impl Fn(&[u8], &[u8]) -> Ordering + Copy {
+        let ordering = *self;
+        move |a, b| ordering.order(a, b)
+    }
+
+    fn order(&self, a: &[u8], b: &[u8]) -> Ordering {
+        if self.integer {
+            return match (integer(a), integer(b)) {
+                (Some(x), Some(y)) => x.cmp(&y),
+                _ => a.len().cmp(&b.len()).then_with(|| a.cmp(b)),
+            };
+        }
+        let a = bytes_to_val(a);
+        let b = bytes_to_val(b);
+        unsafe { (self.cmp)(&a, &b) }.cmp(&0)
+    }
+}
+
+/// A native-endian `MDBX_INTEGERKEY` value.
+fn integer(bytes: &[u8]) -> Option<u64> {
+    match bytes.len() {
+        4 => Some(u32::from_ne_bytes(bytes.try_into().ok()?).into()),
+        8 => Some(u64::from_ne_bytes(bytes.try_into().ok()?)),
+        _ => None,
+    }
+}
+
+impl fmt::Debug for KeyOrdering {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KeyOrdering").finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lexicographic() {
+        let ordering = KeyOrdering::for_flags(DbFlags::empty()).unwrap();
+        assert_eq!(ordering.compare(b"a", b"b").unwrap(), Ordering::Less);
+        assert_eq!(ordering.compare(b"ab", b"a").unwrap(), Ordering::Greater);
+        assert_eq!(ordering.compare(b"", b"").unwrap(), Ordering::Equal);
+
+        let mut keys = [&b"b"[..], b"ab", b"a", b"ba"];
+        ordering.sort(&mut keys).unwrap();
+        assert_eq!(keys, [&b"a"[..], b"ab", b"b", b"ba"]);
+    }
+
+    #[test]
+    fn test_reverse_key() {
+        let ordering = KeyOrdering::for_flags(DbFlags::REVERSEKEY).unwrap();
+        // Compared from the last byte backwards
+        assert_eq!(ordering.compare(b"ba", b"ab").unwrap(), Ordering::Less);
+        assert_eq!(ordering.compare(b"a", b"ba").unwrap(), Ordering::Less);
+
+        let mut keys = [&b"ab"[..], b"ba", b"a"];
+        ordering.sort(&mut keys).unwrap();
+        assert_eq!(keys, [&b"a"[..], b"ba", b"ab"]);
+    }
+
+    #[test]
+    fn test_integer_key() {
+        let ordering = KeyOrdering::for_flags(DbFlags::INTEGERKEY).unwrap();
+        let (one, big) = (1u64.to_ne_bytes(), (1u64 << 40).to_ne_bytes());
+        assert_eq!(ordering.compare(&one, &big).unwrap(), Ordering::Less);
+        assert_eq!(
+            ordering
+                .compare(&7u32.to_ne_bytes(), &300u32.to_ne_bytes())
+                .unwrap(),
+            Ordering::Less
+        );
+
+        let mut keys: Vec<_> = [256u32, 1, 65536, 2].map(u32::to_ne_bytes).into();
+        ordering.sort(&mut keys).unwrap();
+        assert_eq!(keys, [1u32, 2, 256, 65536].map(u32::to_ne_bytes));
+
+        let cmp = ordering.order_fn();
+        let mut keys: Vec<_> = [300u64, 1 << 40, 7].map(u64::to_ne_bytes).into();
+        keys.sort_by(|a, b| cmp(a, b));
+        assert_eq!(keys, [7u64, 300, 1 << 40].map(u64::to_ne_bytes));
+
+        let dup =
+            KeyOrdering::for_dup_flags(DbFlags::DUPSORT | DbFlags::DUPFIXED | DbFlags::INTEGERDUP)
+                .unwrap();
+        assert_eq!(dup.compare(&big, &one).unwrap(), Ordering::Greater);
+    }
+
+    #[test]
+    fn test_integer_key_rejects_lengths() {
+        let ordering = KeyOrdering::for_flags(DbFlags::INTEGERKEY).unwrap();
+        let bad_size = |result: Result<Ordering>| result == Err(Error::from_code(MDBX_BAD_VALSIZE));
+        assert!(bad_size(ordering.compare(b"abc", b"abc")));
+        assert!(bad_size(ordering.compare(&[0; 4], &[0; 5])));
+        assert!(bad_size(ordering.compare(&[0; 4], &[0; 8])));
+        assert!(bad_size((ordering.as_fn())(b"", &[0; 8])));
+        assert!(ordering.check(&[0; 8]).is_ok());
+        assert!(ordering.check(&[0; 2]).is_err());
+
+        let mut keys = [vec![0; 4], vec![0; 8]];
+        assert!(ordering.sort(&mut keys).is_err());
+        assert_eq!(ordering.order_fn()(&[0; 3], &[0; 8]), Ordering::Less);
+    }
+}
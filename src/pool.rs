@@ -0,0 +1,521 @@
+//! Pool of reusable read-only transactions.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::mem::ManuallyDrop;
+use std::ops::{Deref, DerefMut};
+use std::ptr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Condvar, Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
+
+use crate::constants::MDBX_SUCCESS;
+use crate::cursor::Cursor;
+use crate::database::Database;
+use crate::environment::Environment;
+use crate::error::{mdbx_result, Error, Result};
+use crate::flags::{EnvFlags, TxnFlags};
+use crate::transaction::{begin, RoTxn, Txn};
+use crate::*;
+
+/// A pool of read-only transactions for short, frequent reads.
+///
+/// Reset transactions are kept by the pool and renewed on checkout with
+/// `mdbx_txn_renew`, so each request keeps its reader slot instead of
+/// acquiring a new one.
+///
+/// Transactions move between threads, so the environment must be opened
+/// with `MDBX_NOSTICKYTHREADS`.
+///
+/// ```rust,ignore
+/// let pool = ReadTxnPool::new(&env, 64)?;
+/// let txn = pool.get()?;
+/// let mut cursor = txn.cursor(db)?;
+/// let first = cursor.first()?;
+/// ```
+pub struct ReadTxnPool<'env> {
+    env: &'env Environment,
+    max_txns: usize,
+    state: Mutex<PoolState>,
+    available: Condvar,
+    counters: PoolCounters,
+}
+
+struct PoolState {
+    idle: Vec<Slot>,
+    /// Slots handed out or idle.
+    open: usize,
+}
+
+/// A reset transaction with the cursors it owns, grouped by database.
+struct Slot {
+    txn: *mut MDBX_txn,
+    cursors: CursorStash,
+}
+
+type CursorStash = HashMap<MDBX_dbi, Vec<*mut MDBX_cursor>>;
+
+// SAFETY: the pool requires `MDBX_NOSTICKYTHREADS`, which lets transactions
+// and their cursors be used from any thread, one at a time.
+unsafe impl Send for Slot {}
+
+impl Slot {
+    /// Close the cursors, then abort the transaction.
+    fn close(self) {
+        unsafe {
+            for cursor in self.cursors.into_values().flatten() {
+                mdbx_cursor_close(cursor);
+            }
+            mdbx_txn_abort(self.txn);
+        }
+    }
+}
+
+#[derive(Default)]
+struct PoolCounters {
+    created: AtomicU64,
+    reused: AtomicU64,
+    cursors_opened: AtomicU64,
+    cursors_reused: AtomicU64,
+    waits: AtomicU64,
+    wait_nanos: AtomicU64,
+}
+
+/// Counters of a [`ReadTxnPool`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PoolStats {
+    /// Transactions begun by the pool.
+    pub created: u64,
+    /// Checkouts served by renewing a pooled transaction.
+    pub reused: u64,
+    /// Cursors opened by pooled transactions.
+    pub cursors_opened: u64,
+    /// Cursors served by renewing a pooled cursor.
+    pub cursors_reused: u64,
+    /// Checkouts that waited for a free slot.
+    pub waits: u64,
+    /// Total time spent waiting for a free slot.
+    pub wait_time: Duration,
+    /// Transactions currently checked out.
+    pub in_use: usize,
+    /// Reset transactions waiting to be reused.
+    pub idle: usize,
+}
+
+impl<'env> ReadTxnPool<'env> {
+    /// Pool of at most `max_txns` transactions, capped at the environment's
+    /// `maxreaders`.
+    pub fn new(env: &'env Environment, max_txns: usize) -> Result<Self> {
+        if !env.flags()?.contains(EnvFlags::NOSTICKYTHREADS) {
+            return Err(Error::InvalidFlags(
+                "ReadTxnPool requires MDBX_NOSTICKYTHREADS",
+            ));
+        }
+        if max_txns == 0 {
+            return Err(Error::InvalidOption("ReadTxnPool needs at least one slot"));
+        }
+        Ok(ReadTxnPool {
+            env,
+            max_txns: max_txns.min(env.max_readers()? as usize),
+            state: Mutex::new(PoolState {
+                idle: Vec::new(),
+                open: 0,
+            }),
+            available: Condvar::new(),
+            counters: PoolCounters::default(),
+        })
+    }
+
+    /// Maximum number of transactions the pool opens.
+    #[inline]
+    pub fn max_txns(&self) -> usize {
+        self.max_txns
+    }
+
+    /// Reserve reader slots for up to `count` transactions in advance with
+    /// `MDBX_TXN_RDONLY_PREPARE`.
+    pub fn prepare(&self, count: usize) -> Result<()> {
+        for _ in 0..count {
+            {
+                let mut state = self.lock();
+                if state.open >= self.max_txns {
+                    return Ok(());
+                }
+                state.open += 1;
+            }
+            match begin(self.env, ptr::null_mut(), TxnFlags::RDONLY_PREPARE) {
+                Ok((txn, _span)) => {
+                    self.counters.created.fetch_add(1, Ordering::Relaxed);
+                    self.release(Some(Slot {
+                        txn,
+                        cursors: CursorStash::new(),
+                    }));
+                }
+                Err(err) => {
+                    self.release(None);
+                    return Err(err);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Check out a transaction, waiting while all slots are in use.
+    pub fn get(&self) -> Result<PooledTxn<'_, 'env>> {
+        let mut state = self.lock();
+        let mut waited = None;
+        let slot = loop {
+            if let Some(slot) = state.idle.pop() {
+                break Some(slot);
+            }
+            if state.open < self.max_txns {
+                state.open += 1;
+                break None;
+            }
+            waited.get_or_insert_with(Instant::now);
+            state = self
+                .available
+                .wait(state)
+                .unwrap_or_else(PoisonError::into_inner);
+        };
+        drop(state);
+        if let Some(start) = waited {
+            self.counters.waits.fetch_add(1, Ordering::Relaxed);
+            let nanos = start.elapsed().as_nanos().min(u64::MAX as u128) as u64;
+            self.counters.wait_nanos.fetch_add(nanos, Ordering::Relaxed);
+        }
+        self.checkout(slot)
+    }
+
+    /// Check out a transaction if one is available without waiting.
+    pub fn try_get(&self) -> Result<Option<PooledTxn<'_, 'env>>> {
+        let mut state = self.lock();
+        let slot = match state.idle.pop() {
+            Some(slot) => Some(slot),
+            None if state.open < self.max_txns => {
+                state.open += 1;
+                None
+            }
+            None => return Ok(None),
+        };
+        drop(state);
+        self.checkout(slot).map(Some)
+    }
+
+    /// Renew `slot`, or begin a new transaction for a freshly counted one.
+    fn checkout(&self, slot: Option<Slot>) -> Result<PooledTxn<'_, 'env>> {
+        let (txn, cursors) = match slot {
+            Some(slot) => {
+                if let Err(err) = mdbx_result(unsafe { mdbx_txn_renew(slot.txn) }) {
+                    slot.close();
+                    self.release(None);
+                    return Err(err);
+                }
+                self.counters.reused.fetch_add(1, Ordering::Relaxed);
+                (RoTxn::from_raw(slot.txn), slot.cursors)
+            }
+            None => match RoTxn::begin(self.env) {
+                Ok(txn) => {
+                    self.counters.created.fetch_add(1, Ordering::Relaxed);
+                    (txn, CursorStash::new())
+                }
+                Err(err) => {
+                    self.release(None);
+                    return Err(err);
+                }
+            },
+        };
+        Ok(PooledTxn {
+            pool: self,
+            txn: ManuallyDrop::new(txn),
+            cursors: RefCell::new(cursors),
+        })
+    }
+
+    /// Return a slot to the pool, or give up a closed one.
+    fn release(&self, slot: Option<Slot>) {
+        let mut state = self.lock();
+        match slot {
+            Some(slot) => state.idle.push(slot),
+            None => state.open -= 1,
+        }
+        drop(state);
+        self.available.notify_one();
+    }
+
+    /// Current counters.
+    pub fn stats(&self) -> PoolStats {
+        let (open, idle) = {
+            let state = self.lock();
+            (state.open, state.idle.len())
+        };
+        let counters = &self.counters;
+        PoolStats {
+            created: counters.created.load(Ordering::Relaxed),
+            reused: counters.reused.load(Ordering::Relaxed),
+            cursors_opened: counters.cursors_opened.load(Ordering::Relaxed),
+            cursors_reused: counters.cursors_reused.load(Ordering::Relaxed),
+            waits: counters.waits.load(Ordering::Relaxed),
+            wait_time: Duration::from_nanos(counters.wait_nanos.load(Ordering::Relaxed)),
+            in_use: open - idle,
+            idle,
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, PoolState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl fmt::Debug for ReadTxnPool<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReadTxnPool")
+            .field("max_txns", &self.max_txns)
+            .field("stats", &self.stats())
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for ReadTxnPool<'_> {
+    fn drop(&mut self) {
+        let state = self.state.get_mut().unwrap_or_else(PoisonError::into_inner);
+        for slot in state.idle.drain(..) {
+            slot.close();
+        }
+    }
+}
+
+/// A read-only transaction checked out of a [`ReadTxnPool`].
+///
+/// Derefs to [`RoTxn`]; dropping it resets the transaction and returns it to
+/// the pool.
+pub struct PooledTxn<'pool, 'env> {
+    pool: &'pool ReadTxnPool<'env>,
+    txn: ManuallyDrop<RoTxn<'env>>,
+    cursors: RefCell<CursorStash>,
+}
+
+impl<'env> PooledTxn<'_, 'env> {
+    /// Open a cursor over `db`, renewing one left by an earlier checkout
+    /// with `mdbx_cursor_renew` when possible.
+    pub fn cursor(&self, db: Database) -> Result<PooledCursor<'_, 'env>> {
+        let pooled = self
+            .cursors
+            .borrow_mut()
+            .get_mut(&db.dbi())
+            .and_then(Vec::pop);
+        let cursor = match pooled {
+            Some(raw) => {
+                if let Err(err) = mdbx_result(unsafe { mdbx_cursor_renew(self.txn.as_ptr(), raw) })
+                {
+                    unsafe { mdbx_cursor_close(raw) };
+                    return Err(err);
+                }
+                self.pool
+                    .counters
+                    .cursors_reused
+                    .fetch_add(1, Ordering::Relaxed);
+                unsafe { Cursor::from_raw(&*self.txn, db, raw) }
+            }
+            None => {
+                let cursor = Cursor::open(&*self.txn, db)?;
+                self.pool
+                    .counters
+                    .cursors_opened
+                    .fetch_add(1, Ordering::Relaxed);
+                cursor
+            }
+        };
+        Ok(PooledCursor {
+            cursor: ManuallyDrop::new(cursor),
+            stash: &self.cursors,
+        })
+    }
+}
+
+impl<'env> Deref for PooledTxn<'_, 'env> {
+    type Target = RoTxn<'env>;
+
+    fn deref(&self) -> &RoTxn<'env> {
+        &self.txn
+    }
+}
+
+impl fmt::Debug for PooledTxn<'_, '_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PooledTxn")
+            .field("txn", &*self.txn)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for PooledTxn<'_, '_> {
+    fn drop(&mut self) {
+        // SAFETY: `self.txn` is not used again.
+        let txn = unsafe { ManuallyDrop::take(&mut self.txn) }.into_raw();
+        let slot = Slot {
+            txn,
+            cursors: self.cursors.take(),
+        };
+        if unsafe { mdbx_txn_reset(txn) } == MDBX_SUCCESS {
+            self.pool.release(Some(slot));
+        } else {
+            slot.close();
+            self.pool.release(None);
+        }
+    }
+}
+
+/// A cursor of a [`PooledTxn`], kept for reuse when dropped.
+pub struct PooledCursor<'txn, 'env> {
+    cursor: ManuallyDrop<Cursor<'txn, RoTxn<'env>>>,
+    stash: &'txn RefCell<CursorStash>,
+}
+
+impl<'txn, 'env> Deref for PooledCursor<'txn, 'env> {
+    type Target = Cursor<'txn, RoTxn<'env>>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.cursor
+    }
+}
+
+impl DerefMut for PooledCursor<'_, '_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.cursor
+    }
+}
+
+impl fmt::Debug for PooledCursor<'_, '_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&*self.cursor, f)
+    }
+}
+
+impl Drop for PooledCursor<'_, '_> {
+    fn drop(&mut self) {
+        // SAFETY: `self.cursor` is not used again.
+        let cursor = unsafe { ManuallyDrop::take(&mut self.cursor) };
+        let dbi = cursor.db().dbi();
+        let raw = cursor.into_raw();
+        self.stash.borrow_mut().entry(dbi).or_default().push(raw);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flags::{DbFlags, PutFlags};
+    use crate::test_util::TestEnv;
+
+    fn test_env() -> TestEnv {
+        TestEnv::with(Environment::builder().flags(EnvFlags::NOSTICKYTHREADS))
+    }
+
+    fn put(env: &Environment, key: &[u8]) -> Database {
+        let txn = env.begin_rw_txn().unwrap();
+        let db = txn.create_db(None, DbFlags::empty()).unwrap();
+        txn.put(db, key, b"value", PutFlags::empty()).unwrap();
+        txn.commit().unwrap();
+        db
+    }
+
+    #[test]
+    fn test_requires_nostickythreads() {
+        let env = TestEnv::new();
+        assert!(matches!(
+            ReadTxnPool::new(&env, 4),
+            Err(Error::InvalidFlags(_))
+        ));
+        let env = test_env();
+        assert!(matches!(
+            ReadTxnPool::new(&env, 0),
+            Err(Error::InvalidOption(_))
+        ));
+    }
+
+    #[test]
+    fn test_reuse_sees_new_commits() {
+        let env = test_env();
+        let db = put(&env, b"first");
+        let pool = ReadTxnPool::new(&env, 4).unwrap();
+
+        let txn = pool.get().unwrap();
+        let first_id = txn.id();
+        assert!(txn.get(db, b"second").unwrap().is_none());
+        drop(txn);
+        assert_eq!(pool.stats().idle, 1);
+
+        put(&env, b"second");
+        let txn = pool.get().unwrap();
+        assert!(txn.id() > first_id);
+        assert!(txn.get(db, b"second").unwrap().is_some());
+        let stats = pool.stats();
+        assert_eq!((stats.created, stats.reused), (1, 1));
+        assert_eq!((stats.in_use, stats.idle), (1, 0));
+        drop(txn);
+
+        drop(pool.get().unwrap());
+        assert_eq!(pool.stats().reused, 2);
+    }
+
+    #[test]
+    fn test_cursor_stash() {
+        let env = test_env();
+        let db = put(&env, b"key");
+        let pool = ReadTxnPool::new(&env, 1).unwrap();
+
+        let txn = pool.get().unwrap();
+        let mut cursor = txn.cursor(db).unwrap();
+        assert_eq!(&*cursor.first().unwrap().unwrap().0, b"key");
+        drop(cursor);
+        drop(txn);
+
+        let txn = pool.get().unwrap();
+        let mut cursor = txn.cursor(db).unwrap();
+        assert_eq!(&*cursor.first().unwrap().unwrap().0, b"key");
+        // The stash is empty while the renewed cursor is in use
+        let mut second = txn.cursor(db).unwrap();
+        assert_eq!(&*second.last().unwrap().unwrap().0, b"key");
+        let stats = pool.stats();
+        assert_eq!((stats.cursors_opened, stats.cursors_reused), (2, 1));
+    }
+
+    #[test]
+    fn test_max_txns_waits() {
+        let env = test_env();
+        let pool = ReadTxnPool::new(&env, usize::MAX).unwrap();
+        assert_eq!(pool.max_txns(), env.max_readers().unwrap() as usize);
+
+        let pool = ReadTxnPool::new(&env, 1).unwrap();
+        let txn = pool.get().unwrap();
+        assert!(pool.try_get().unwrap().is_none());
+        std::thread::scope(|scope| {
+            let waiter = scope.spawn(|| drop(pool.get().unwrap()));
+            // Give the waiter time to block on the only slot
+            std::thread::sleep(Duration::from_millis(50));
+            drop(txn);
+            waiter.join().unwrap();
+        });
+        let stats = pool.stats();
+        assert_eq!(stats.waits, 1);
+        assert!(stats.wait_time > Duration::ZERO);
+        assert_eq!((stats.created, stats.reused, stats.idle), (1, 1, 1));
+    }
+
+    #[test]
+    fn test_prepare() {
+        let env = test_env();
+        let db = put(&env, b"key");
+        let pool = ReadTxnPool::new(&env, 2).unwrap();
+        pool.prepare(3).unwrap();
+        let stats = pool.stats();
+        assert_eq!((stats.created, stats.idle, stats.in_use), (2, 2, 0));
+
+        let txn = pool.get().unwrap();
+        assert!(txn.get(db, b"key").unwrap().is_some());
+        let stats = pool.stats();
+        assert_eq!((stats.created, stats.reused), (2, 1));
+    }
+}
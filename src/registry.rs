@@ -0,0 +1,284 @@
+//! Process-wide registry of open environments.
+//!
+//! The engine's file locks are per process, so opening the same database
+//! twice in one process breaks reader accounting. Every open is recorded
+//! here until the environment is closed: shared opens get the environment
+//! that is already open, other opens of a registered file fail.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError, Weak};
+
+use crate::environment::Environment;
+use crate::error::{io_error, Error, Result};
+use crate::MDBX_env_flags_t;
+
+static REGISTRY: Mutex<Registry> = Mutex::new(Registry {
+    entries: Vec::new(),
+    next_id: 0,
+});
+/// Signalled whenever a registered environment has been closed.
+static CLOSED: Condvar = Condvar::new();
+
+struct Registry {
+    entries: Vec<Entry>,
+    next_id: u64,
+}
+
+struct Entry {
+    id: u64,
+    file: FileId,
+    /// Flags and handle of a shared open; `None` for exclusive opens.
+    shared: Option<(MDBX_env_flags_t, Weak<Environment>)>,
+}
+
+/// What the registry knows about a file.
+enum Found {
+    Free,
+    Exclusive,
+    Shared(MDBX_env_flags_t, Arc<Environment>),
+}
+
+/// Identity of a database path: its canonical form and, on Unix, the device
+/// and inode it resolves to, so hard links are recognized too.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct FileId {
+    path: PathBuf,
+    inode: Option<(u64, u64)>,
+}
+
+impl FileId {
+    /// Identify `path`, or `None` if it doesn't exist yet.
+    fn of(path: &Path) -> Result<Option<Self>> {
+        let path = match fs::canonicalize(path) {
+            Ok(path) => path,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(io_error(err)),
+        };
+        let inode = inode(&fs::metadata(&path).map_err(io_error)?);
+        Ok(Some(FileId { path, inode }))
+    }
+
+    fn same_file(&self, other: &FileId) -> bool {
+        self.path == other.path || (self.inode.is_some() && self.inode == other.inode)
+    }
+}
+
+#[cfg(unix)]
+fn inode(metadata: &fs::Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    Some((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+fn inode(_metadata: &fs::Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+fn lock() -> MutexGuard<'static, Registry> {
+    REGISTRY.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Look up `file`, waiting for environments that are being closed.
+///
+/// The returned handle must be dropped after the guard, since dropping the
+/// last handle unregisters the environment.
+fn find(
+    mut registry: MutexGuard<'static, Registry>,
+    file: &FileId,
+) -> (MutexGuard<'static, Registry>, Found) {
+    loop {
+        let found = match registry
+            .entries
+            .iter()
+            .find(|entry| entry.file.same_file(file))
+        {
+            None => Found::Free,
+            Some(Entry { shared: None, .. }) => Found::Exclusive,
+            Some(Entry {
+                shared: Some((flags, env)),
+                ..
+            }) => match env.upgrade() {
+                Some(env) => Found::Shared(*flags, env),
+                // Dropped, but `mdbx_env_close` hasn't returned yet
+                None => {
+                    registry = CLOSED
+                        .wait(registry)
+                        .unwrap_or_else(PoisonError::into_inner);
+                    continue;
+                }
+            },
+        };
+        return (registry, found);
+    }
+}
+
+/// Record `env`, just opened as `file`, until it is closed.
+fn register(
+    registry: &mut Registry,
+    file: Option<FileId>,
+    env: &mut Environment,
+    shared: Option<(MDBX_env_flags_t, Weak<Environment>)>,
+) {
+    // The engine creates missing files, so this is only `None` for
+    // environments that aren't on a file system
+    if let Some(file) = file {
+        let id = registry.next_id;
+        registry.next_id += 1;
+        env.set_registration(id);
+        registry.entries.push(Entry { id, file, shared });
+    }
+}
+
+/// Open `path` with `open` unless the file is already open in the process.
+///
+/// Fails with `AlreadyOpen` if it is.
+pub(crate) fn open_exclusive(
+    path: &Path,
+    open: impl FnOnce() -> Result<Environment>,
+) -> Result<Environment> {
+    // Held across `open`, so two threads can't both open the same file
+    let registry = lock();
+    let mut registry = match FileId::of(path)? {
+        Some(file) => match find(registry, &file) {
+            (registry, Found::Free) => registry,
+            (registry, found) => {
+                drop(registry);
+                drop(found);
+                return Err(Error::AlreadyOpen(path.to_path_buf()));
+            }
+        },
+        None => registry,
+    };
+    let mut env = open()?;
+    let file = FileId::of(path)?;
+    register(&mut registry, file, &mut env, None);
+    Ok(env)
+}
+
+/// Return the environment opened for `path` by an earlier shared open, or
+/// register the one returned by `open`.
+///
+/// Fails with `InvalidFlags` if the environment is open with other flags and
+/// with `AlreadyOpen` if the file was opened exclusively.
+pub(crate) fn open_shared(
+    path: &Path,
+    flags: MDBX_env_flags_t,
+    open: impl FnOnce() -> Result<Environment>,
+) -> Result<Arc<Environment>> {
+    let registry = lock();
+    let mut registry = match FileId::of(path)? {
+        Some(file) => match find(registry, &file) {
+            (registry, Found::Free) => registry,
+            (registry, Found::Exclusive) => {
+                drop(registry);
+                return Err(Error::AlreadyOpen(path.to_path_buf()));
+            }
+            (registry, Found::Shared(registered, env)) => {
+                drop(registry);
+                if registered != flags {
+                    return Err(Error::InvalidFlags(
+                        "environment is already open with different flags",
+                    ));
+                }
+                return Ok(env);
+            }
+        },
+        None => registry,
+    };
+    let mut env = open()?;
+    let file = FileId::of(path)?;
+    Ok(Arc::new_cyclic(|weak| {
+        register(&mut registry, file, &mut env, Some((flags, weak.clone())));
+        env
+    }))
+}
+
+/// Forget the environment registered as `id` once it has been closed.
+pub(crate) fn closed(id: u64) {
+    lock().entries.retain(|entry| entry.id != id);
+    CLOSED.notify_all();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flags::EnvFlags;
+
+    #[test]
+    fn test_file_id() {
+        let dir = std::env::temp_dir().join(format!("mdbx-rs-registry-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("data");
+        fs::write(&file, b"").unwrap();
+
+        let id = FileId::of(&file).unwrap().unwrap();
+        let dotted = FileId::of(&dir.join(".").join("data")).unwrap().unwrap();
+        assert!(id.same_file(&dotted));
+        assert!(!id.same_file(&FileId::of(&dir).unwrap().unwrap()));
+        assert_eq!(FileId::of(&dir.join("missing")).unwrap(), None);
+
+        #[cfg(unix)]
+        {
+            let link = dir.join("link");
+            fs::hard_link(&file, &link).unwrap();
+            let linked = FileId::of(&link).unwrap().unwrap();
+            assert_ne!(id.path, linked.path);
+            assert!(id.same_file(&linked));
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_open_shared() {
+        let dir = tempfile::tempdir().unwrap();
+        let builder = Environment::builder().max_dbs(4);
+        let env = builder.open_shared(dir.path()).unwrap();
+        let again = builder.open_shared(dir.path().join(".")).unwrap();
+        assert!(Arc::ptr_eq(&env, &again));
+
+        let err = Environment::builder()
+            .flags(EnvFlags::NORDAHEAD)
+            .open_shared(dir.path())
+            .unwrap_err();
+        assert!(matches!(err, Error::InvalidFlags(_)));
+        assert!(matches!(
+            builder.open(dir.path()),
+            Err(Error::AlreadyOpen(_))
+        ));
+
+        drop((env, again));
+        let env = builder.open(dir.path()).unwrap();
+        assert!(matches!(
+            builder.open_shared(dir.path()),
+            Err(Error::AlreadyOpen(_))
+        ));
+        drop(env);
+        builder.open_shared(dir.path()).unwrap();
+    }
+
+    #[test]
+    fn test_reopen_while_closing() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().to_path_buf();
+        // Every open either succeeds or is refused by the registry; the
+        // engine never sees the file opened twice
+        let check = |result: Result<()>| match result {
+            Ok(()) | Err(Error::AlreadyOpen(_)) => {}
+            Err(err) => panic!("unexpected error: {}", err),
+        };
+        let shared = std::thread::spawn({
+            let path = path.clone();
+            move || {
+                for _ in 0..20 {
+                    check(Environment::builder().open_shared(&path).map(drop));
+                }
+            }
+        });
+        for _ in 0..20 {
+            check(Environment::builder().open(&path).map(drop));
+        }
+        shared.join().unwrap();
+    }
+}
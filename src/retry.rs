@@ -0,0 +1,134 @@
+//! Retry policy for [`Environment::read`] and [`Environment::write`].
+//!
+//! [`Environment::read`]: crate::Environment::read
+//! [`Environment::write`]: crate::Environment::write
+
+use std::time::Duration;
+
+use crate::constants::{MDBX_BUSY, MDBX_MVCC_RETARDED, MDBX_OUSTED, MDBX_TXN_OVERLAPPING};
+use crate::error::Error;
+
+/// How often and how patiently transient errors are retried.
+///
+/// `MDBX_BUSY`, `MDBX_MVCC_RETARDED`, `MDBX_OUSTED` and
+/// `MDBX_TXN_OVERLAPPING` are retried after an exponential backoff.
+///
+/// ```rust,ignore
+/// let env = Environment::builder()
+///     .retry_policy(RetryPolicy::new(5).try_writer(true))
+///     .open("./mydb")?;
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RetryPolicy {
+    max_retries: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    try_writer: bool,
+}
+
+impl RetryPolicy {
+    /// Retry up to `max_retries` times.
+    pub fn new(max_retries: u32) -> Self {
+        RetryPolicy {
+            max_retries,
+            ..Default::default()
+        }
+    }
+
+    /// Fail on the first error.
+    pub fn none() -> Self {
+        RetryPolicy::new(0)
+    }
+
+    /// Backoff before the first retry, doubled for each further one.
+    pub fn backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max;
+        self
+    }
+
+    /// Begin write transactions with `MDBX_TXN_TRY`, so a held writer lock
+    /// fails with `MDBX_BUSY` and is retried instead of blocking.
+    pub fn try_writer(mut self, enabled: bool) -> Self {
+        self.try_writer = enabled;
+        self
+    }
+
+    /// Maximum number of retries after the first attempt.
+    #[inline]
+    pub fn max_retries(&self) -> u32 {
+        self.max_retries
+    }
+
+    /// Whether write transactions are begun with `MDBX_TXN_TRY`.
+    #[inline]
+    pub fn is_try_writer(&self) -> bool {
+        self.try_writer
+    }
+
+    /// Whether `err` is worth retrying.
+    pub fn is_retryable(err: &Error) -> bool {
+        matches!(
+            err,
+            Error::Mdbx(MDBX_BUSY | MDBX_MVCC_RETARDED | MDBX_OUSTED | MDBX_TXN_OVERLAPPING)
+        )
+    }
+
+    /// Delay before retry number `retry` (starting at 0), or `None` once
+    /// the retries are used up.
+    pub(crate) fn delay(&self, retry: u32) -> Option<Duration> {
+        if retry >= self.max_retries {
+            return None;
+        }
+        let factor = 1u32.checked_shl(retry).unwrap_or(u32::MAX);
+        Some(
+            self.initial_backoff
+                .saturating_mul(factor)
+                .min(self.max_backoff),
+        )
+    }
+}
+
+impl Default for RetryPolicy {
+    /// Up to 8 retries, backing off from 1ms to 100ms.
+    fn default() -> Self {
+        RetryPolicy {
+            max_retries: 8,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(100),
+            try_writer: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::{MDBX_MAP_FULL, MDBX_NOTFOUND};
+
+    #[test]
+    fn test_retryable_codes() {
+        for code in [
+            MDBX_BUSY,
+            MDBX_MVCC_RETARDED,
+            MDBX_OUSTED,
+            MDBX_TXN_OVERLAPPING,
+        ] {
+            assert!(RetryPolicy::is_retryable(&Error::from_code(code)));
+        }
+        assert!(!RetryPolicy::is_retryable(&Error::from_code(MDBX_MAP_FULL)));
+        assert!(!RetryPolicy::is_retryable(&Error::from_code(MDBX_NOTFOUND)));
+        assert!(!RetryPolicy::is_retryable(&Error::InvalidFlags("")));
+    }
+
+    #[test]
+    fn test_delay() {
+        let policy =
+            RetryPolicy::new(4).backoff(Duration::from_millis(10), Duration::from_millis(50));
+        let delays: Vec<_> = (0..5).map(|retry| policy.delay(retry)).collect();
+        let ms = |ms| Some(Duration::from_millis(ms));
+        assert_eq!(delays, [ms(10), ms(20), ms(40), ms(50), None]);
+        assert_eq!(RetryPolicy::none().delay(0), None);
+        assert_eq!(RetryPolicy::new(100).delay(40), ms(100));
+    }
+}
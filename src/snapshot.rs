@@ -0,0 +1,335 @@
+//! Shared read snapshots with owned, `'static` values and iterators.
+
+use std::fmt;
+use std::mem::ManuallyDrop;
+use std::ops::{Deref, RangeBounds};
+use std::ptr;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use crate::cursor::Item;
+use crate::database::Database;
+use crate::environment::Environment;
+use crate::error::{Error, Result};
+use crate::flags::{EnvFlags, TxnFlags};
+use crate::iter::{Iter, KeyRange};
+use crate::transaction::{begin, RoTxn, Txn};
+use crate::value::Value;
+
+/// A read-only transaction shared through an `Arc`.
+///
+/// The snapshot stays open as long as any clone, [`OwnedValue`] or
+/// [`OwnedIter`] created from it is alive, and all of them are
+/// `Send + 'static`, so values can be handed to other tasks without copying.
+/// Engine calls on the snapshot are serialized internally.
+///
+/// Sharing a transaction across threads requires an environment opened with
+/// `MDBX_NOSTICKYTHREADS`.
+///
+/// ```rust,ignore
+/// let snapshot = env.snapshot()?;
+/// let db = snapshot.open_db(Some("blobs"))?;
+/// let blob = snapshot.get(db, b"key")?;
+/// tokio::spawn(async move { respond(blob.as_deref()).await });
+/// ```
+#[derive(Clone)]
+pub struct Snapshot {
+    inner: Arc<SnapshotInner>,
+}
+
+struct SnapshotInner {
+    txn: RoTxn<'static>,
+    lock: Mutex<()>,
+    env: Arc<Environment>,
+}
+
+// SAFETY: the environment uses `MDBX_NOSTICKYTHREADS`, so the transaction may
+// be used from any thread, and `lock` serializes the engine calls on it.
+// Values borrow immutable pages of the snapshot.
+unsafe impl Send for SnapshotInner {}
+unsafe impl Sync for SnapshotInner {}
+
+impl Snapshot {
+    /// Begin a snapshot of `env`.
+    pub fn new(env: Arc<Environment>) -> Result<Self> {
+        if !env.flags()?.contains(EnvFlags::NOSTICKYTHREADS) {
+            return Err(Error::InvalidFlags(
+                "Snapshot requires MDBX_NOSTICKYTHREADS",
+            ));
+        }
+        let (txn, span) = begin(&env, ptr::null_mut(), TxnFlags::RDONLY)?;
+        Ok(Snapshot {
+            inner: Arc::new(SnapshotInner {
+                // `env` is kept alive by the same `SnapshotInner`
+                txn: RoTxn::from_parts(txn, span),
+                lock: Mutex::new(()),
+                env,
+            }),
+        })
+    }
+
+    /// The environment this snapshot reads.
+    #[inline]
+    pub fn env(&self) -> &Arc<Environment> {
+        &self.inner.env
+    }
+
+    /// Id of the snapshot's transaction.
+    pub fn id(&self) -> u64 {
+        let _lock = self.lock();
+        self.inner.txn.id()
+    }
+
+    /// Open an existing database, or the main database if `name` is `None`.
+    pub fn open_db(&self, name: Option<&str>) -> Result<Database> {
+        let _lock = self.lock();
+        self.inner.txn.open_db(name)
+    }
+
+    /// Get the value stored under `key`.
+    pub fn get(&self, db: Database, key: &[u8]) -> Result<Option<OwnedValue>> {
+        let value = {
+            let _lock = self.lock();
+            self.inner.txn.get(db, key)?
+        };
+        Ok(value.map(|value| self.own(value)))
+    }
+
+    /// Iterate over all entries of `db`.
+    pub fn iter(&self, db: Database) -> Result<OwnedIter> {
+        self.owned_iter(|txn| Iter::new(txn, db, KeyRange::full()))
+    }
+
+    /// Iterate over the entries of `db` whose keys fall within `range`.
+    pub fn range<'k, R: RangeBounds<&'k [u8]>>(&self, db: Database, range: R) -> Result<OwnedIter> {
+        self.owned_iter(|txn| Iter::new(txn, db, KeyRange::new(&range)))
+    }
+
+    /// Iterate over the entries of `db` whose keys start with `prefix`.
+    pub fn prefix(&self, db: Database, prefix: &[u8]) -> Result<OwnedIter> {
+        self.owned_iter(|txn| Iter::prefix(txn, db, prefix))
+    }
+
+    fn owned_iter(
+        &self,
+        open: impl FnOnce(&'static RoTxn<'static>) -> Result<Iter<'static, RoTxn<'static>>>,
+    ) -> Result<OwnedIter> {
+        // SAFETY: the transaction lives in the `Arc` allocation, which the
+        // iterator keeps alive and drops only after the inner iterator.
+        let txn = unsafe { &*(&self.inner.txn as *const RoTxn<'static>) };
+        let iter = {
+            let _lock = self.lock();
+            open(txn)?
+        };
+        Ok(OwnedIter {
+            iter: ManuallyDrop::new(iter),
+            snapshot: self.clone(),
+        })
+    }
+
+    /// Tie `value` to this snapshot instead of a borrow of it.
+    fn own(&self, value: Value<'_>) -> OwnedValue {
+        let value = match value {
+            // SAFETY: the bytes stay mapped while the snapshot is open, which
+            // the returned value ensures.
+            Value::Borrowed(bytes) => Value::Borrowed(unsafe { &*(bytes as *const [u8]) }),
+            Value::Owned(bytes) => Value::Owned(bytes),
+        };
+        OwnedValue {
+            value,
+            _snapshot: self.clone(),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, ()> {
+        self.inner
+            .lock
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl fmt::Debug for Snapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Snapshot")
+            .field("txn", &self.inner.txn)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A value that keeps its [`Snapshot`] open, usually without copying.
+#[derive(Clone)]
+pub struct OwnedValue {
+    value: Value<'static>,
+    _snapshot: Snapshot,
+}
+
+impl OwnedValue {
+    /// Whether the value borrows directly from the memory map.
+    #[inline]
+    pub fn is_borrowed(&self) -> bool {
+        self.value.is_borrowed()
+    }
+}
+
+impl Deref for OwnedValue {
+    type Target = [u8];
+
+    #[inline]
+    fn deref(&self) -> &[u8] {
+        &self.value
+    }
+}
+
+impl AsRef<[u8]> for OwnedValue {
+    #[inline]
+    fn as_ref(&self) -> &[u8] {
+        self
+    }
+}
+
+impl PartialEq for OwnedValue {
+    fn eq(&self, other: &Self) -> bool {
+        **self == **other
+    }
+}
+
+impl Eq for OwnedValue {}
+
+impl fmt::Debug for OwnedValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.value, f)
+    }
+}
+
+/// An iterator that keeps its [`Snapshot`] open.
+pub struct OwnedIter {
+    iter: ManuallyDrop<Iter<'static, RoTxn<'static>>>,
+    snapshot: Snapshot,
+}
+
+// SAFETY: see `SnapshotInner`; every use of the cursors holds the lock.
+unsafe impl Send for OwnedIter {}
+
+impl OwnedIter {
+    fn step(
+        &mut self,
+        next: impl FnOnce(&mut Iter<'static, RoTxn<'static>>) -> Option<Result<Item<'static>>>,
+    ) -> Option<Result<(OwnedValue, OwnedValue)>> {
+        let item = {
+            let _lock = self.snapshot.lock();
+            next(&mut self.iter)?
+        };
+        Some(item.map(|(key, data)| (self.snapshot.own(key), self.snapshot.own(data))))
+    }
+}
+
+impl Iterator for OwnedIter {
+    type Item = Result<(OwnedValue, OwnedValue)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.step(Iterator::next)
+    }
+}
+
+impl DoubleEndedIterator for OwnedIter {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.step(DoubleEndedIterator::next_back)
+    }
+}
+
+impl std::iter::FusedIterator for OwnedIter {}
+
+impl fmt::Debug for OwnedIter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OwnedIter")
+            .field("iter", &*self.iter)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for OwnedIter {
+    fn drop(&mut self) {
+        let _lock = self.snapshot.lock();
+        // SAFETY: `self.iter` is not used again, and is dropped before the
+        // snapshot it borrows.
+        unsafe { ManuallyDrop::drop(&mut self.iter) };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flags::{DbFlags, PutFlags};
+    use crate::test_util::TestEnv;
+
+    fn assert_send_static<T: Send + Sync + 'static>() {}
+
+    #[test]
+    fn test_owned_types_are_send() {
+        assert_send_static::<Snapshot>();
+        assert_send_static::<OwnedValue>();
+        fn assert_send<T: Send + 'static>() {}
+        assert_send::<OwnedIter>();
+    }
+
+    #[test]
+    fn test_outlives_handles() {
+        let dir = tempfile::tempdir().unwrap();
+        let env = Environment::builder()
+            .flags(EnvFlags::NOSTICKYTHREADS)
+            .open(dir.path())
+            .map(Arc::new)
+            .unwrap();
+        let txn = env.begin_rw_txn().unwrap();
+        let db = txn.create_db(None, DbFlags::empty()).unwrap();
+        let old = vec![7; 3000];
+        txn.put(db, b"a", &old, PutFlags::empty()).unwrap();
+        txn.put(db, b"b", b"old", PutFlags::empty()).unwrap();
+        txn.commit().unwrap();
+
+        let snapshot = env.snapshot().unwrap();
+        let value = snapshot.get(db, b"a").unwrap().unwrap();
+        assert!(value.is_borrowed());
+        let iter = snapshot.iter(db).unwrap();
+        let writer = Arc::clone(&env);
+        drop((snapshot, env));
+
+        // Overwrite everything a few times while only the values hold the
+        // snapshot open
+        for round in 0..4u8 {
+            let txn = writer.begin_rw_txn().unwrap();
+            txn.put(db, b"a", &[round; 3000], PutFlags::empty())
+                .unwrap();
+            txn.put(db, b"b", b"new", PutFlags::empty()).unwrap();
+            txn.put(db, &[b'c', round], b"new", PutFlags::empty())
+                .unwrap();
+            txn.commit().unwrap();
+        }
+        drop(writer);
+
+        let expected = [
+            (b"a".to_vec(), old.clone()),
+            (b"b".to_vec(), b"old".to_vec()),
+        ];
+        let entries = std::thread::spawn(move || {
+            assert_eq!(*value, *old);
+            iter.map(|item| {
+                let (key, data) = item.unwrap();
+                (key.to_vec(), data.to_vec())
+            })
+            .collect::<Vec<_>>()
+        })
+        .join()
+        .unwrap();
+        assert_eq!(entries, expected);
+    }
+
+    #[test]
+    fn test_requires_nostickythreads() {
+        let env = TestEnv::new();
+        assert_eq!(
+            Snapshot::new(Arc::clone(&env.env)).unwrap_err(),
+            Error::InvalidFlags("Snapshot requires MDBX_NOSTICKYTHREADS")
+        );
+    }
+}
@@ -0,0 +1,306 @@
+//! Typed tables.
+
+use std::fmt;
+use std::iter::FusedIterator;
+use std::marker::PhantomData;
+use std::ops::{Bound, RangeBounds};
+
+use crate::codec::{KeyCodec, ValueCodec};
+use crate::database::Database;
+use crate::error::{Error, Result};
+use crate::flags::{DbFlags, PutFlags};
+use crate::iter::{Iter, KeyRange};
+use crate::transaction::{RwTxn, Txn};
+
+/// A table declared once with its name, flags and codecs.
+///
+/// ```rust,ignore
+/// struct Users;
+///
+/// impl TableDef for Users {
+///     const NAME: &'static str = "users";
+///     type Key = u64;
+///     type Value = Postcard<User>;
+/// }
+///
+/// let users = txn.create_table::<Users>()?;
+/// // later, in any transaction
+/// let users = txn.open_table::<Users>()?;
+/// users.put(&txn, &42, &user)?;
+/// ```
+pub trait TableDef {
+    /// Name of the database.
+    const NAME: &'static str;
+    /// Flags the database is created with.
+    ///
+    /// The built-in integer codecs are big-endian, so don't combine them with
+    /// `MDBX_INTEGERKEY`.
+    const FLAGS: DbFlags = DbFlags::empty();
+    /// Key codec.
+    type Key: KeyCodec + ?Sized;
+    /// Value codec.
+    type Value: ValueCodec + ?Sized;
+}
+
+/// A database whose keys and values are converted with codecs.
+pub struct Table<K: KeyCodec + ?Sized, V: ValueCodec + ?Sized> {
+    db: Database,
+    _codecs: PhantomData<fn(&K, &V)>,
+}
+
+impl<K: KeyCodec + ?Sized, V: ValueCodec + ?Sized> Table<K, V> {
+    /// Use an already opened database with these codecs.
+    pub fn from_db(db: Database) -> Self {
+        Table {
+            db,
+            _codecs: PhantomData,
+        }
+    }
+
+    /// Open an existing table, or the main database if `name` is `None`.
+    pub fn open(txn: &impl Txn, name: Option<&str>) -> Result<Self> {
+        txn.open_db(name).map(Table::from_db)
+    }
+
+    /// Open a table, creating it if it doesn't exist.
+    pub fn create(txn: &RwTxn<'_>, name: Option<&str>, flags: DbFlags) -> Result<Self> {
+        txn.create_db(name, flags).map(Table::from_db)
+    }
+
+    /// The underlying database.
+    #[inline]
+    pub fn db(&self) -> Database {
+        self.db
+    }
+
+    /// Get the value stored under `key`.
+    pub fn get<'txn>(
+        &self,
+        txn: &'txn impl Txn,
+        key: &K::Item,
+    ) -> Result<Option<V::Decoded<'txn>>> {
+        let key = K::encode(key)?;
+        txn.get(self.db, key.as_ref())?.map(V::decode).transpose()
+    }
+
+    /// Store `value` under `key`.
+    pub fn put(&self, txn: &RwTxn<'_>, key: &K::Item, value: &V::Item) -> Result<()> {
+        self.put_with_flags(txn, key, value, PutFlags::empty())
+    }
+
+    /// Store `value` under `key` with explicit put flags.
+    pub fn put_with_flags(
+        &self,
+        txn: &RwTxn<'_>,
+        key: &K::Item,
+        value: &V::Item,
+        flags: PutFlags,
+    ) -> Result<()> {
+        let key = K::encode(key)?;
+        let value = V::encode(value)?;
+        txn.put(self.db, key.as_ref(), value.as_ref(), flags)
+    }
+
+    /// Delete `key`. Returns `false` if it didn't exist.
+    pub fn delete(&self, txn: &RwTxn<'_>, key: &K::Item) -> Result<bool> {
+        let key = K::encode(key)?;
+        txn.del(self.db, key.as_ref(), None)
+    }
+
+    /// Iterate over all entries.
+    pub fn iter<'txn, T: Txn>(&self, txn: &'txn T) -> Result<TableIter<'txn, T, K, V>> {
+        Ok(TableIter::new(txn.iter(self.db)?))
+    }
+
+    /// Iterate over the entries whose keys fall within `range`.
+    pub fn range<'txn, 'k, T, R>(&self, txn: &'txn T, range: R) -> Result<TableIter<'txn, T, K, V>>
+    where
+        T: Txn,
+        R: RangeBounds<&'k K::Item>,
+        K::Item: 'k,
+    {
+        let encode = |bound: Bound<&&K::Item>| -> Result<Bound<Vec<u8>>> {
+            Ok(match bound {
+                Bound::Included(key) => Bound::Included(K::encode(key)?.as_ref().to_vec()),
+                Bound::Excluded(key) => Bound::Excluded(K::encode(key)?.as_ref().to_vec()),
+                Bound::Unbounded => Bound::Unbounded,
+            })
+        };
+        let range = KeyRange::from_bounds(encode(range.start_bound())?, encode(range.end_bound())?);
+        Ok(TableIter::new(Iter::new(txn, self.db, range)?))
+    }
+}
+
+impl<K: KeyCodec + ?Sized, V: ValueCodec + ?Sized> Clone for Table<K, V> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<K: KeyCodec + ?Sized, V: ValueCodec + ?Sized> Copy for Table<K, V> {}
+
+impl<K: KeyCodec + ?Sized, V: ValueCodec + ?Sized> fmt::Debug for Table<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Table").field("db", &self.db).finish()
+    }
+}
+
+/// Wrap `db` opened for `D`, checking its flags match the definition.
+pub(crate) fn from_def<D: TableDef + ?Sized>(db: Database) -> Result<Table<D::Key, D::Value>> {
+    check_flags(db.flags(), D::FLAGS)?;
+    Ok(Table::from_db(db))
+}
+
+fn check_flags(actual: DbFlags, declared: DbFlags) -> Result<()> {
+    let persistent = !(DbFlags::CREATE | DbFlags::ACCEDE);
+    if actual & persistent != declared & persistent {
+        return Err(Error::InvalidFlags(
+            "table flags differ from its definition",
+        ));
+    }
+    Ok(())
+}
+
+/// Iterator over the decoded entries of a [`Table`].
+pub struct TableIter<'txn, T: Txn, K: KeyCodec + ?Sized, V: ValueCodec + ?Sized> {
+    inner: Iter<'txn, T>,
+    _codecs: PhantomData<fn(&K, &V)>,
+}
+
+impl<'txn, T: Txn, K: KeyCodec + ?Sized, V: ValueCodec + ?Sized> TableIter<'txn, T, K, V> {
+    fn new(inner: Iter<'txn, T>) -> Self {
+        TableIter {
+            inner,
+            _codecs: PhantomData,
+        }
+    }
+
+    fn decode(
+        item: Result<crate::cursor::Item<'txn>>,
+    ) -> Result<(K::Decoded<'txn>, V::Decoded<'txn>)> {
+        let (key, value) = item?;
+        Ok((K::decode(key)?, V::decode(value)?))
+    }
+}
+
+impl<'txn, T: Txn, K: KeyCodec + ?Sized, V: ValueCodec + ?Sized> Iterator
+    for TableIter<'txn, T, K, V>
+{
+    type Item = Result<(K::Decoded<'txn>, V::Decoded<'txn>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(Self::decode)
+    }
+}
+
+impl<T: Txn, K: KeyCodec + ?Sized, V: ValueCodec + ?Sized> DoubleEndedIterator
+    for TableIter<'_, T, K, V>
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back().map(Self::decode)
+    }
+}
+
+impl<T: Txn, K: KeyCodec + ?Sized, V: ValueCodec + ?Sized> FusedIterator
+    for TableIter<'_, T, K, V>
+{
+}
+
+impl<T: Txn + fmt::Debug, K: KeyCodec + ?Sized, V: ValueCodec + ?Sized> fmt::Debug
+    for TableIter<'_, T, K, V>
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TableIter")
+            .field("inner", &self.inner)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use super::*;
+    use crate::test_util::TestEnv;
+
+    struct Names;
+
+    impl TableDef for Names {
+        const NAME: &'static str = "names";
+        type Key = u64;
+        type Value = str;
+    }
+
+    struct DupNames;
+
+    impl TableDef for DupNames {
+        const NAME: &'static str = "names";
+        const FLAGS: DbFlags = DbFlags::DUPSORT;
+        type Key = u64;
+        type Value = str;
+    }
+
+    fn collect<'a>(iter: impl Iterator<Item = Result<(u64, Cow<'a, str>)>>) -> Vec<(u64, String)> {
+        iter.map(|item| item.map(|(key, value)| (key, value.into_owned())))
+            .collect::<Result<_>>()
+            .unwrap()
+    }
+
+    fn pairs(items: &[(u64, &str)]) -> Vec<(u64, String)> {
+        items
+            .iter()
+            .map(|&(key, value)| (key, value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_table_def() {
+        let env = TestEnv::new();
+        let txn = env.begin_rw_txn().unwrap();
+        let names = txn.create_table::<Names>().unwrap();
+        for (key, name) in [
+            (3, "three"),
+            (1, "one"),
+            (256, "many"),
+            (2, "two"),
+            (5, "five"),
+        ] {
+            names.put(&txn, &key, name).unwrap();
+        }
+        assert_eq!(names.get(&txn, &3).unwrap().as_deref(), Some("three"));
+        assert!(names.delete(&txn, &5).unwrap());
+        assert!(!names.delete(&txn, &5).unwrap());
+        assert_eq!(names.get(&txn, &5).unwrap(), None);
+        txn.commit().unwrap();
+
+        let txn = env.begin_ro_txn().unwrap();
+        let names = txn.open_table::<Names>().unwrap();
+        assert_eq!(names.get(&txn, &256).unwrap().as_deref(), Some("many"));
+        assert_eq!(
+            collect(names.range(&txn, &2..&5).unwrap()),
+            pairs(&[(2, "two"), (3, "three")])
+        );
+        assert_eq!(
+            collect(names.iter(&txn).unwrap().rev()),
+            pairs(&[(256, "many"), (3, "three"), (2, "two"), (1, "one")])
+        );
+        assert_eq!(
+            collect(names.range(&txn, &2..).unwrap().rev()),
+            pairs(&[(256, "many"), (3, "three"), (2, "two")])
+        );
+
+        assert_eq!(
+            txn.open_table::<DupNames>().unwrap_err(),
+            Error::InvalidFlags("table flags differ from its definition")
+        );
+    }
+
+    #[test]
+    fn test_check_flags() {
+        let dupsort = DbFlags::DUPSORT;
+        assert!(check_flags(dupsort, dupsort).is_ok());
+        assert!(check_flags(dupsort, dupsort | DbFlags::CREATE).is_ok());
+        assert!(check_flags(DbFlags::empty(), dupsort).is_err());
+        assert!(check_flags(DbFlags::REVERSEKEY, DbFlags::empty()).is_err());
+    }
+}
@@ -0,0 +1,39 @@
+//! Environments in temporary directories for tests.
+
+use std::ops::Deref;
+use std::sync::Arc;
+
+use tempfile::TempDir;
+
+use crate::environment::{Environment, EnvironmentBuilder};
+
+/// An environment in a temporary directory, removed after the environment
+/// is closed.
+pub(crate) struct TestEnv {
+    pub env: Arc<Environment>,
+    _dir: TempDir,
+}
+
+impl TestEnv {
+    /// Environment with room for a few named tables.
+    pub fn new() -> Self {
+        TestEnv::with(Environment::builder().max_dbs(8))
+    }
+
+    pub fn with(builder: EnvironmentBuilder) -> Self {
+        let dir = tempfile::tempdir().unwrap();
+        let env = builder.open(dir.path()).unwrap();
+        TestEnv {
+            env: Arc::new(env),
+            _dir: dir,
+        }
+    }
+}
+
+impl Deref for TestEnv {
+    type Target = Environment;
+
+    fn deref(&self) -> &Environment {
+        &self.env
+    }
+}
@@ -0,0 +1,265 @@
+//! `tracing` spans for transactions, commits, table opens and scans, and
+//! map growth events.
+//!
+//! Only the spans around single engine calls (`mdbx.txn_begin`,
+//! `mdbx.commit`, `mdbx.open_table`) are entered. The lifetime spans
+//! `mdbx.txn` and `mdbx.scan` are never entered, since transactions and
+//! iterators outlive any scope and may move between threads; they only
+//! carry fields and parent the commit span.
+//!
+//! Without the `tracing` feature every helper compiles to a no-op.
+
+#[cfg(feature = "tracing")]
+pub(crate) use enabled::*;
+
+#[cfg(not(feature = "tracing"))]
+pub(crate) use disabled::*;
+
+#[cfg(feature = "tracing")]
+mod enabled {
+    use std::time::Duration;
+
+    use tracing::field::Empty;
+    pub(crate) use tracing::Span;
+
+    use crate::latency::CommitLatency;
+    use crate::{mdbx_txn_id, MDBX_dbi, MDBX_txn};
+
+    /// Span around `mdbx_txn_begin`, covering the wait for the writer lock.
+    pub(crate) fn begin_span(read_write: bool) -> Span {
+        tracing::debug_span!("mdbx.txn_begin", read_write)
+    }
+
+    /// Span covering the lifetime of a transaction; never entered.
+    pub(crate) fn txn_span(txn: *mut MDBX_txn, read_write: bool) -> Span {
+        let txn_id = unsafe { mdbx_txn_id(txn) };
+        tracing::info_span!("mdbx.txn", txn_id, read_write)
+    }
+
+    /// Span around `mdbx_txn_commit_ex`, filled in by [`record_latency`].
+    pub(crate) fn commit_span(txn: &Span) -> Span {
+        tracing::info_span!(
+            parent: txn,
+            "mdbx.commit",
+            preparation_us = Empty,
+            gc_wallclock_us = Empty,
+            audit_us = Empty,
+            write_us = Empty,
+            sync_us = Empty,
+            ending_us = Empty,
+            whole_us = Empty,
+            gc_cputime_us = Empty,
+        )
+    }
+
+    /// Attach the commit phases to a span from [`commit_span`].
+    pub(crate) fn record_latency(span: &Span, latency: &CommitLatency) {
+        let micros = |d: Duration| d.as_micros() as u64;
+        span.record("preparation_us", micros(latency.preparation));
+        span.record("gc_wallclock_us", micros(latency.gc_wallclock));
+        span.record("audit_us", micros(latency.audit));
+        span.record("write_us", micros(latency.write));
+        span.record("sync_us", micros(latency.sync));
+        span.record("ending_us", micros(latency.ending));
+        span.record("whole_us", micros(latency.whole));
+        span.record("gc_cputime_us", micros(latency.gc_cputime));
+    }
+
+    /// Span around `mdbx_dbi_open`.
+    pub(crate) fn open_table_span(name: Option<&str>) -> Span {
+        tracing::debug_span!("mdbx.open_table", table = name.unwrap_or("@main"))
+    }
+
+    /// Span covering the lifetime of a range iterator; never entered.
+    pub(crate) fn scan_span(dbi: MDBX_dbi) -> Span {
+        tracing::debug_span!("mdbx.scan", dbi, entries = Empty)
+    }
+
+    /// Attach the number of entries yielded to a span from [`scan_span`].
+    pub(crate) fn record_scan(span: &Span, entries: u64) {
+        span.record("entries", entries);
+    }
+
+    /// Event for a map growth after `MDBX_MAP_FULL`.
+    pub(crate) fn map_grown(upper: u64) {
+        tracing::info!(upper_bytes = upper, "mdbx map grown");
+    }
+}
+
+#[cfg(not(feature = "tracing"))]
+mod disabled {
+    use crate::latency::CommitLatency;
+    use crate::{MDBX_dbi, MDBX_txn};
+
+    /// Stand-in for `tracing::Span`.
+    #[derive(Debug, Clone)]
+    pub(crate) struct Span;
+
+    impl Span {
+        #[inline]
+        pub(crate) fn in_scope<T>(&self, f: impl FnOnce() -> T) -> T {
+            f()
+        }
+    }
+
+    #[inline]
+    pub(crate) fn begin_span(_read_write: bool) -> Span {
+        Span
+    }
+
+    #[inline]
+    pub(crate) fn txn_span(_txn: *mut MDBX_txn, _read_write: bool) -> Span {
+        Span
+    }
+
+    #[inline]
+    pub(crate) fn commit_span(_txn: &Span) -> Span {
+        Span
+    }
+
+    #[inline]
+    pub(crate) fn record_latency(_span: &Span, _latency: &CommitLatency) {}
+
+    #[inline]
+    pub(crate) fn open_table_span(_name: Option<&str>) -> Span {
+        Span
+    }
+
+    #[inline]
+    pub(crate) fn scan_span(_dbi: MDBX_dbi) -> Span {
+        Span
+    }
+
+    #[inline]
+    pub(crate) fn record_scan(_span: &Span, _entries: u64) {}
+
+    #[inline]
+    pub(crate) fn map_grown(_upper: u64) {}
+}
+
+#[cfg(all(test, feature = "tracing"))]
+mod tests {
+    use std::collections::HashMap;
+    use std::fmt;
+    use std::sync::{Arc, Mutex};
+
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Metadata, Subscriber};
+
+    use crate::flags::{DbFlags, PutFlags};
+    use crate::test_util::TestEnv;
+
+    #[derive(Debug, Default)]
+    struct SpanData {
+        name: &'static str,
+        parent: Option<u64>,
+        fields: HashMap<&'static str, String>,
+        entered: bool,
+    }
+
+    impl Visit for SpanData {
+        fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+            self.fields.insert(field.name(), format!("{:?}", value));
+        }
+
+        fn record_str(&mut self, field: &Field, value: &str) {
+            self.fields.insert(field.name(), value.to_owned());
+        }
+    }
+
+    /// Records every span with its fields, parent and whether it was entered.
+    #[derive(Clone, Default)]
+    struct Recorder {
+        spans: Arc<Mutex<Vec<SpanData>>>,
+        stack: Arc<Mutex<Vec<u64>>>,
+    }
+
+    impl Subscriber for Recorder {
+        fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, attrs: &Attributes<'_>) -> Id {
+            let parent = match attrs.parent() {
+                Some(parent) => Some(parent.into_u64()),
+                None if attrs.is_contextual() => self.stack.lock().unwrap().last().copied(),
+                None => None,
+            };
+            let mut span = SpanData {
+                name: attrs.metadata().name(),
+                parent,
+                ..SpanData::default()
+            };
+            attrs.record(&mut span);
+            let mut spans = self.spans.lock().unwrap();
+            spans.push(span);
+            Id::from_u64(spans.len() as u64)
+        }
+
+        fn record(&self, span: &Id, values: &Record<'_>) {
+            values.record(&mut self.spans.lock().unwrap()[span.into_u64() as usize - 1]);
+        }
+
+        fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+        fn event(&self, _event: &Event<'_>) {}
+
+        fn enter(&self, span: &Id) {
+            self.spans.lock().unwrap()[span.into_u64() as usize - 1].entered = true;
+            self.stack.lock().unwrap().push(span.into_u64());
+        }
+
+        fn exit(&self, _span: &Id) {
+            self.stack.lock().unwrap().pop();
+        }
+    }
+
+    #[test]
+    fn test_txn_and_commit_spans() {
+        let env = TestEnv::new();
+        let recorder = Recorder::default();
+        let txnid = tracing::subscriber::with_default(recorder.clone(), || {
+            let txn = env.begin_rw_txn().unwrap();
+            let db = txn.create_db(None, DbFlags::empty()).unwrap();
+            txn.put(db, b"key", b"value", PutFlags::empty()).unwrap();
+            let txnid = crate::Txn::id(&txn);
+            txn.commit().unwrap();
+            txnid
+        });
+
+        let spans = recorder.spans.lock().unwrap();
+        let find = |name| {
+            let index = spans.iter().position(|span| span.name == name).unwrap();
+            (index as u64 + 1, &spans[index])
+        };
+        let (_, begin) = find("mdbx.txn_begin");
+        assert!(begin.entered);
+        assert_eq!(begin.fields["read_write"], "true");
+
+        let (txn_id, txn) = find("mdbx.txn");
+        assert!(!txn.entered);
+        assert_eq!(txn.fields["txn_id"], txnid.to_string());
+        assert_eq!(txn.fields["read_write"], "true");
+
+        let (_, open) = find("mdbx.open_table");
+        assert!(open.entered);
+        assert_eq!(open.fields["table"], "@main");
+
+        let (_, commit) = find("mdbx.commit");
+        assert!(commit.entered);
+        assert_eq!(commit.parent, Some(txn_id));
+        for field in [
+            "preparation_us",
+            "gc_wallclock_us",
+            "audit_us",
+            "write_us",
+            "sync_us",
+            "ending_us",
+            "whole_us",
+            "gc_cputime_us",
+        ] {
+            assert!(commit.fields[field].parse::<u64>().is_ok(), "{}", field);
+        }
+    }
+}
@@ -0,0 +1,664 @@
+//! Safe read-only and read-write transactions.
+
+use std::ffi::CString;
+use std::marker::PhantomData;
+use std::mem::ManuallyDrop;
+use std::ops::RangeBounds;
+use std::os::raw::{c_uint, c_void};
+use std::ptr;
+
+use crate::changes::{Capture, ChangeSet};
+use crate::cursor::{Cursor, CursorMut};
+use crate::database::Database;
+use crate::environment::Environment;
+use crate::error::{mdbx_result, Error, Result};
+use crate::flags::{DbFlags, PutFlags, TxnFlags};
+use crate::info::TxnInfo;
+use crate::iter::{Iter, KeyRange};
+use crate::latency::CommitLatency;
+use crate::table::{self, Table, TableDef};
+use crate::trace::{self, Span};
+use crate::value::Value;
+use crate::*;
+
+mod private {
+    pub trait Sealed {
+        /// Whether values may live in pages dirtied by this transaction.
+        const READ_WRITE: bool;
+    }
+}
+
+/// Operations shared by read-only and read-write transactions.
+pub trait Txn: private::Sealed {
+    /// Raw transaction handle for use with the FFI functions.
+    fn as_ptr(&self) -> *mut MDBX_txn;
+
+    /// Transaction id (snapshot number for readers).
+    fn id(&self) -> u64 {
+        unsafe { mdbx_txn_id(self.as_ptr()) }
+    }
+
+    /// Space usage of this transaction.
+    fn info(&self) -> Result<TxnInfo> {
+        let mut info = MDBX_txn_info::default();
+        mdbx_result(unsafe { mdbx_txn_info(self.as_ptr(), &mut info, false) })?;
+        Ok(TxnInfo::from(info))
+    }
+
+    /// Open an existing database, or the main database if `name` is `None`.
+    fn open_db(&self, name: Option<&str>) -> Result<Database> {
+        open_db(self.as_ptr(), name, DbFlags::ACCEDE)
+    }
+
+    /// Get the value stored under `key`.
+    ///
+    /// Values in pages dirtied by a write transaction are copied, everything
+    /// else borrows from the memory map.
+    fn get<'txn>(&'txn self, db: Database, key: &[u8]) -> Result<Option<Value<'txn>>> {
+        let key = bytes_to_val(key);
+        let mut data = MDBX_val::default();
+        match unsafe { mdbx_get(self.as_ptr(), db.dbi(), &key, &mut data) } {
+            MDBX_SUCCESS => unsafe { value_from_val(self, &data) }.map(Some),
+            MDBX_NOTFOUND => Ok(None),
+            rc => Err(Error::from_code(rc)),
+        }
+    }
+
+    /// Open the table described by `D`.
+    ///
+    /// Fails with `InvalidFlags` if the table was created with other flags.
+    fn open_table<D: TableDef + ?Sized>(&self) -> Result<Table<D::Key, D::Value>> {
+        table::from_def::<D>(self.open_db(Some(D::NAME))?)
+    }
+
+    /// Open a cursor over `db`.
+    fn cursor(&self, db: Database) -> Result<Cursor<'_, Self>> {
+        Cursor::open(self, db)
+    }
+
+    /// Iterate over all entries of `db`.
+    fn iter(&self, db: Database) -> Result<Iter<'_, Self>> {
+        Iter::new(self, db, KeyRange::full())
+    }
+
+    /// Iterate over the entries of `db` whose keys fall within `range`.
+    ///
+    /// ```rust,ignore
+    /// let (from, to): (&[u8], &[u8]) = (b"a", b"m");
+    /// for item in txn.range(db, from..to)?.rev() {
+    ///     let (key, value) = item?;
+    /// }
+    /// ```
+    fn range<'k, R: RangeBounds<&'k [u8]>>(
+        &self,
+        db: Database,
+        range: R,
+    ) -> Result<Iter<'_, Self>> {
+        Iter::new(self, db, KeyRange::new(&range))
+    }
+
+    /// Iterate over the entries of `db` whose keys start with `prefix`.
+    ///
+    /// On `MDBX_REVERSEKEY` and `MDBX_INTEGERKEY` tables such keys are not
+    /// adjacent, so the whole table is scanned and filtered.
+    fn prefix(&self, db: Database, prefix: &[u8]) -> Result<Iter<'_, Self>> {
+        Iter::prefix(self, db, prefix)
+    }
+}
+
+/// Wrap a value returned by the engine, copying it if it lies in a dirty page.
+///
+/// # Safety
+///
+/// `val` must have been returned by the engine for `txn`.
+pub(crate) unsafe fn value_from_val<'txn, T: Txn + ?Sized>(
+    txn: &'txn T,
+    val: &MDBX_val,
+) -> Result<Value<'txn>> {
+    let bytes: &'txn [u8] = val_to_bytes(val);
+    if T::READ_WRITE && !bytes.is_empty() && is_dirty(txn.as_ptr(), bytes)? {
+        Ok(Value::Owned(bytes.to_vec()))
+    } else {
+        Ok(Value::Borrowed(bytes))
+    }
+}
+
+fn is_dirty(txn: *mut MDBX_txn, bytes: &[u8]) -> Result<bool> {
+    match unsafe { mdbx_is_dirty(txn, bytes.as_ptr() as *const c_void) } {
+        MDBX_RESULT_TRUE => Ok(true),
+        MDBX_RESULT_FALSE => Ok(false),
+        rc => Err(Error::from_code(rc)),
+    }
+}
+
+pub(crate) fn open_db(txn: *mut MDBX_txn, name: Option<&str>, flags: DbFlags) -> Result<Database> {
+    flags.validate()?;
+    let span = trace::open_table_span(name);
+    let name = name
+        .map(|name| CString::new(name).map_err(|_| Error::from_code(MDBX_EINVAL)))
+        .transpose()?;
+    let name_ptr = name.as_ref().map_or(ptr::null(), |name| name.as_ptr());
+
+    let mut dbi: MDBX_dbi = 0;
+    mdbx_result(span.in_scope(|| unsafe { mdbx_dbi_open(txn, name_ptr, flags.bits(), &mut dbi) }))?;
+
+    let mut db_flags: c_uint = 0;
+    let mut state: c_uint = 0;
+    mdbx_result(unsafe { mdbx_dbi_flags_ex(txn, dbi, &mut db_flags, &mut state) })?;
+    Ok(Database::new(dbi, DbFlags::from_bits_retain(db_flags)))
+}
+
+pub(crate) fn begin(
+    env: &Environment,
+    parent: *mut MDBX_txn,
+    flags: TxnFlags,
+) -> Result<(*mut MDBX_txn, Span)> {
+    flags.validate()?;
+    let read_write = !flags.contains(TxnFlags::RDONLY);
+    let mut txn = ptr::null_mut();
+    trace::begin_span(read_write).in_scope(|| {
+        mdbx_result(unsafe { mdbx_txn_begin(env.as_ptr(), parent, flags.bits(), &mut txn) })
+    })?;
+    Ok((txn, trace::txn_span(txn, read_write)))
+}
+
+/// A read-only transaction over a consistent snapshot.
+///
+/// The transaction is aborted when dropped.
+#[derive(Debug)]
+pub struct RoTxn<'env> {
+    txn: *mut MDBX_txn,
+    _span: Span,
+    _env: PhantomData<&'env Environment>,
+}
+
+impl<'env> RoTxn<'env> {
+    pub(crate) fn begin(env: &'env Environment) -> Result<Self> {
+        let (txn, span) = begin(env, ptr::null_mut(), TxnFlags::RDONLY)?;
+        Ok(RoTxn::from_parts(txn, span))
+    }
+
+    /// Take ownership of a running read-only transaction.
+    pub(crate) fn from_raw(txn: *mut MDBX_txn) -> Self {
+        RoTxn::from_parts(txn, trace::txn_span(txn, false))
+    }
+
+    /// Take ownership of a read-only transaction and its span.
+    ///
+    /// The caller is responsible for keeping the environment open for `'env`.
+    pub(crate) fn from_parts(txn: *mut MDBX_txn, span: Span) -> Self {
+        RoTxn {
+            txn,
+            _span: span,
+            _env: PhantomData,
+        }
+    }
+
+    /// Give up ownership of the handle without aborting it.
+    pub(crate) fn into_raw(self) -> *mut MDBX_txn {
+        let mut this = ManuallyDrop::new(self);
+        // SAFETY: `this` is never dropped, so the span is dropped only once.
+        unsafe { ptr::drop_in_place(&mut this._span) };
+        this.txn
+    }
+}
+
+impl private::Sealed for RoTxn<'_> {
+    const READ_WRITE: bool = false;
+}
+
+impl Txn for RoTxn<'_> {
+    #[inline]
+    fn as_ptr(&self) -> *mut MDBX_txn {
+        self.txn
+    }
+}
+
+impl Drop for RoTxn<'_> {
+    fn drop(&mut self) {
+        unsafe {
+            mdbx_txn_abort(self.txn);
+        }
+    }
+}
+
+/// A read-write transaction.
+///
+/// Changes become visible to other transactions only after
+/// [`commit`](RwTxn::commit). The transaction is aborted when dropped.
+#[derive(Debug)]
+pub struct RwTxn<'env> {
+    txn: *mut MDBX_txn,
+    env: &'env Environment,
+    span: Span,
+    capture: Option<Capture<'env>>,
+}
+
+impl<'env> RwTxn<'env> {
+    pub(crate) fn begin(env: &'env Environment, flags: TxnFlags) -> Result<Self> {
+        let (txn, span) = begin(env, ptr::null_mut(), flags)?;
+        Ok(RwTxn {
+            txn,
+            env,
+            span,
+            capture: env.change_feed().map(|_| Capture::default()),
+        })
+    }
+
+    /// Begin a child transaction of this one.
+    ///
+    /// The child borrows `self` mutably, so the parent can't be used until
+    /// the child is committed, which merges its changes into the parent, or
+    /// aborted, which discards only the child's changes.
+    ///
+    /// ```compile_fail,E0502
+    /// # use mdbx_rs::{Environment, PutFlags};
+    /// # fn savepoint(env: &Environment) -> mdbx_rs::Result<()> {
+    /// let mut txn = env.begin_rw_txn()?;
+    /// let db = txn.create_db(None, Default::default())?;
+    /// let child = txn.begin_nested()?;
+    /// txn.put(db, b"key", b"parent", PutFlags::empty())?;
+    /// child.commit()?;
+    /// # txn.commit()
+    /// # }
+    /// ```
+    pub fn begin_nested(&mut self) -> Result<RwTxn<'_>> {
+        let (txn, span) = begin(self.env, self.txn, TxnFlags::empty())?;
+        Ok(RwTxn {
+            txn,
+            env: self.env,
+            span,
+            capture: self.capture.as_ref().map(Capture::nested),
+        })
+    }
+
+    /// Run `f` in a child transaction, committing it into this one if `f`
+    /// succeeds and aborting only the child if it fails.
+    ///
+    /// ```rust,ignore
+    /// let result = txn.nested(|child| {
+    ///     child.put(db, b"key", b"value", PutFlags::empty())?;
+    ///     validate(child)
+    /// });
+    /// ```
+    pub fn nested<R>(&mut self, f: impl FnOnce(&mut RwTxn<'_>) -> Result<R>) -> Result<R> {
+        let mut child = self.begin_nested()?;
+        let value = f(&mut child)?;
+        child.commit()?;
+        Ok(value)
+    }
+
+    /// Open a database, creating it if it doesn't exist.
+    pub fn create_db(&self, name: Option<&str>, flags: DbFlags) -> Result<Database> {
+        open_db(self.txn, name, flags | DbFlags::CREATE)
+    }
+
+    /// Open the table described by `D`, creating it if it doesn't exist.
+    pub fn create_table<D: TableDef + ?Sized>(&self) -> Result<Table<D::Key, D::Value>> {
+        table::from_def::<D>(self.create_db(Some(D::NAME), D::FLAGS)?)
+    }
+
+    /// Store `data` under `key`.
+    pub fn put(&self, db: Database, key: &[u8], data: &[u8], flags: PutFlags) -> Result<()> {
+        flags.validate_for(db.flags())?;
+        let key_val = bytes_to_val(key);
+        let mut data = bytes_to_val(data);
+        mdbx_result(unsafe { mdbx_put(self.txn, db.dbi(), &key_val, &mut data, flags.bits()) })?;
+        self.record(|changes| changes.key(db, key));
+        Ok(())
+    }
+
+    /// Delete `key`, or only the `key`/`data` pair for `MDBX_DUPSORT` databases.
+    ///
+    /// Returns `false` if nothing was deleted.
+    pub fn del(&self, db: Database, key: &[u8], data: Option<&[u8]>) -> Result<bool> {
+        let key_val = bytes_to_val(key);
+        let data = data.map(bytes_to_val);
+        let data_ptr = data
+            .as_ref()
+            .map_or(ptr::null(), |data| data as *const MDBX_val);
+        match unsafe { mdbx_del(self.txn, db.dbi(), &key_val, data_ptr) } {
+            MDBX_SUCCESS => {
+                self.record(|changes| changes.key(db, key));
+                Ok(true)
+            }
+            MDBX_NOTFOUND => Ok(false),
+            rc => Err(Error::from_code(rc)),
+        }
+    }
+
+    /// Delete all entries of a database, keeping the database itself.
+    pub fn clear_db(&self, db: Database) -> Result<()> {
+        mdbx_result(unsafe { mdbx_drop(self.txn, db.dbi(), false) })?;
+        self.record(|changes| changes.table(db));
+        Ok(())
+    }
+
+    /// Delete a database and close its handle.
+    pub fn drop_db(&self, db: Database) -> Result<()> {
+        mdbx_result(unsafe { mdbx_drop(self.txn, db.dbi(), true) })?;
+        self.record(|changes| changes.table(db));
+        Ok(())
+    }
+
+    /// Open a cursor over `db` that borrows the transaction mutably, as
+    /// needed for [`CursorMut::reserve`].
+    pub fn cursor_mut(&mut self, db: Database) -> Result<CursorMut<'_, 'env>> {
+        CursorMut::open(self, db)
+    }
+
+    /// Reserve `len` bytes for `key` with `MDBX_RESERVE` and let `f` fill
+    /// them in place.
+    ///
+    /// The buffer is zeroed before `f` sees it. It lives in a dirty page, so
+    /// the transaction stays mutably borrowed until `f` returns and nothing
+    /// can read the entry meanwhile:
+    ///
+    /// ```compile_fail,E0502
+    /// # use mdbx_rs::{Environment, Txn};
+    /// # fn fill(env: &Environment) -> mdbx_rs::Result<()> {
+    /// let mut txn = env.begin_rw_txn()?;
+    /// let db = txn.create_db(None, Default::default())?;
+    /// txn.reserve(db, b"blob", 4096, |buf| {
+    ///     let current = txn.get(db, b"blob");
+    ///     buf.fill(0xff);
+    /// })?;
+    /// # txn.commit()
+    /// # }
+    /// ```
+    pub fn reserve<R>(
+        &mut self,
+        db: Database,
+        key: &[u8],
+        len: usize,
+        f: impl FnOnce(&mut [u8]) -> R,
+    ) -> Result<R> {
+        PutFlags::RESERVE.validate_for(db.flags())?;
+        let key_val = bytes_to_val(key);
+        let mut data = MDBX_val {
+            iov_base: ptr::null_mut(),
+            iov_len: len,
+        };
+        mdbx_result(unsafe {
+            mdbx_put(
+                self.txn,
+                db.dbi(),
+                &key_val,
+                &mut data,
+                PutFlags::RESERVE.bits(),
+            )
+        })?;
+        self.record(|changes| changes.key(db, key));
+        Ok(unsafe { fill_reserved(&data, f) })
+    }
+
+    /// Record a change for the commit event, if changes are captured.
+    pub(crate) fn record(&self, f: impl FnOnce(&mut ChangeSet)) {
+        if let Some(capture) = &self.capture {
+            capture.record(f);
+        }
+    }
+
+    /// Whether `bytes` lies in a page dirtied by this transaction.
+    ///
+    /// Such memory may move on the next mutation.
+    pub fn is_dirty(&self, bytes: &[u8]) -> Result<bool> {
+        is_dirty(self.txn, bytes)
+    }
+
+    /// Commit the transaction.
+    pub fn commit(self) -> Result<()> {
+        if cfg!(feature = "tracing") || self.env.commit_latency_stats().is_some() {
+            return self.commit_with_latency().map(drop);
+        }
+        let (env, txnid) = (self.env, self.id());
+        let (txn, _span, capture) = self.into_raw();
+        mdbx_result(unsafe { mdbx_txn_commit(txn) })?;
+        if let Some(capture) = capture {
+            capture.committed(env, txnid);
+        }
+        Ok(())
+    }
+
+    /// Commit the transaction and report the time spent in each phase.
+    ///
+    /// The latency is also recorded in the environment's
+    /// [`CommitLatencyStats`](crate::CommitLatencyStats) if enabled.
+    pub fn commit_with_latency(self) -> Result<CommitLatency> {
+        let (env, txnid) = (self.env, self.id());
+        let (txn, txn_span, capture) = self.into_raw();
+        let span = trace::commit_span(&txn_span);
+        let mut latency = MDBX_commit_latency::default();
+        mdbx_result(span.in_scope(|| unsafe { mdbx_txn_commit_ex(txn, &mut latency) }))?;
+        let latency = CommitLatency::from(latency);
+        trace::record_latency(&span, &latency);
+        if let Some(stats) = env.commit_latency_stats() {
+            stats.record(&latency);
+        }
+        if let Some(capture) = capture {
+            capture.committed(env, txnid);
+        }
+        Ok(latency)
+    }
+
+    /// Give up ownership of the handle without aborting it.
+    fn into_raw(self) -> (*mut MDBX_txn, Span, Option<Capture<'env>>) {
+        let this = ManuallyDrop::new(self);
+        // SAFETY: `this` is never dropped, so the span and the captured
+        // changes are moved out only once.
+        unsafe { (this.txn, ptr::read(&this.span), ptr::read(&this.capture)) }
+    }
+
+    /// Abort the transaction, discarding all changes.
+    pub fn abort(self) {
+        drop(self);
+    }
+}
+
+/// Zero the buffer returned by an `MDBX_RESERVE` put and hand it to `f`.
+///
+/// # Safety
+///
+/// `data` must describe a buffer just reserved in a transaction that stays
+/// exclusively borrowed while `f` runs.
+pub(crate) unsafe fn fill_reserved<R>(data: &MDBX_val, f: impl FnOnce(&mut [u8]) -> R) -> R {
+    let buf: &mut [u8] = if data.iov_len == 0 {
+        &mut []
+    } else {
+        // The engine leaves the reserved space uninitialized
+        ptr::write_bytes(data.iov_base as *mut u8, 0, data.iov_len);
+        std::slice::from_raw_parts_mut(data.iov_base as *mut u8, data.iov_len)
+    };
+    f(buf)
+}
+
+impl private::Sealed for RwTxn<'_> {
+    const READ_WRITE: bool = true;
+}
+
+impl Txn for RwTxn<'_> {
+    #[inline]
+    fn as_ptr(&self) -> *mut MDBX_txn {
+        self.txn
+    }
+}
+
+impl Drop for RwTxn<'_> {
+    fn drop(&mut self) {
+        unsafe {
+            mdbx_txn_abort(self.txn);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TestEnv;
+
+    #[test]
+    fn test_reserve_fills_value() {
+        let env = TestEnv::new();
+        let mut txn = env.begin_rw_txn().unwrap();
+        let db = txn.create_db(None, DbFlags::empty()).unwrap();
+        let len = txn
+            .reserve(db, b"blob", 6, |buf| {
+                assert_eq!(buf, [0; 6]);
+                buf[..5].copy_from_slice(b"hello");
+                buf.len()
+            })
+            .unwrap();
+        assert_eq!(len, 6);
+        txn.commit().unwrap();
+
+        let txn = env.begin_ro_txn().unwrap();
+        let value = txn.get(db, b"blob").unwrap().unwrap();
+        assert_eq!(&*value, b"hello\0");
+    }
+
+    #[test]
+    fn test_dirty_values_are_copied() {
+        let env = TestEnv::new();
+        let txn = env.begin_rw_txn().unwrap();
+        let db = txn.create_db(None, DbFlags::empty()).unwrap();
+        txn.put(db, b"old", b"committed", PutFlags::empty())
+            .unwrap();
+        txn.commit().unwrap();
+
+        let txn = env.begin_rw_txn().unwrap();
+        let old = txn.get(db, b"old").unwrap().unwrap();
+        assert!(old.is_borrowed());
+        txn.put(db, b"key", b"first", PutFlags::empty()).unwrap();
+        let value = txn.get(db, b"key").unwrap().unwrap();
+        assert!(matches!(value, Value::Owned(_)));
+
+        // Overwrite the key and split its page while the values are held
+        txn.put(db, b"key", b"second", PutFlags::empty()).unwrap();
+        for i in 0..1000u32 {
+            txn.put(db, &i.to_be_bytes(), &[i as u8; 100], PutFlags::empty())
+                .unwrap();
+        }
+        assert_eq!(&*value, b"first");
+        assert_eq!(&*old, b"committed");
+        assert_eq!(&*txn.get(db, b"key").unwrap().unwrap(), b"second");
+        drop((old, value));
+        txn.commit().unwrap();
+
+        let txn = env.begin_ro_txn().unwrap();
+        let value = txn.get(db, b"key").unwrap().unwrap();
+        assert!(matches!(value, Value::Borrowed(b"second")));
+    }
+
+    fn get(txn: &impl Txn, db: Database, key: &[u8]) -> Option<Vec<u8>> {
+        txn.get(db, key).unwrap().map(|value| value.to_vec())
+    }
+
+    #[test]
+    fn test_nested_commit() {
+        let env = TestEnv::new();
+        let mut txn = env.begin_rw_txn().unwrap();
+        let db = txn.create_db(None, DbFlags::empty()).unwrap();
+        txn.put(db, b"parent", b"1", PutFlags::empty()).unwrap();
+
+        let child = txn.begin_nested().unwrap();
+        assert_eq!(get(&child, db, b"parent"), Some(b"1".to_vec()));
+        child.put(db, b"child", b"2", PutFlags::empty()).unwrap();
+        child.commit().unwrap();
+        assert_eq!(get(&txn, db, b"child"), Some(b"2".to_vec()));
+
+        let value = txn
+            .nested(|child| {
+                child.put(db, b"closure", b"3", PutFlags::empty())?;
+                Ok(7)
+            })
+            .unwrap();
+        assert_eq!(value, 7);
+        txn.commit().unwrap();
+
+        let txn = env.begin_ro_txn().unwrap();
+        assert_eq!(get(&txn, db, b"parent"), Some(b"1".to_vec()));
+        assert_eq!(get(&txn, db, b"child"), Some(b"2".to_vec()));
+        assert_eq!(get(&txn, db, b"closure"), Some(b"3".to_vec()));
+    }
+
+    #[test]
+    fn test_nested_abort() {
+        let env = TestEnv::new();
+        let mut txn = env.begin_rw_txn().unwrap();
+        let db = txn.create_db(None, DbFlags::empty()).unwrap();
+        txn.put(db, b"key", b"parent", PutFlags::empty()).unwrap();
+
+        let child = txn.begin_nested().unwrap();
+        child.put(db, b"key", b"child", PutFlags::empty()).unwrap();
+        child
+            .put(db, b"other", b"child", PutFlags::empty())
+            .unwrap();
+        child.abort();
+        assert_eq!(get(&txn, db, b"key"), Some(b"parent".to_vec()));
+        assert_eq!(get(&txn, db, b"other"), None);
+
+        let err = txn
+            .nested(|child| {
+                child.del(db, b"key", None)?;
+                Err::<(), _>(Error::Codec("rejected".into()))
+            })
+            .unwrap_err();
+        assert_eq!(err, Error::Codec("rejected".into()));
+        assert_eq!(get(&txn, db, b"key"), Some(b"parent".to_vec()));
+        txn.commit().unwrap();
+
+        let txn = env.begin_ro_txn().unwrap();
+        assert_eq!(get(&txn, db, b"key"), Some(b"parent".to_vec()));
+        assert_eq!(get(&txn, db, b"other"), None);
+    }
+
+    #[test]
+    fn test_parent_abort_discards_child() {
+        let env = TestEnv::new();
+        let txn = env.begin_rw_txn().unwrap();
+        let db = txn.create_db(None, DbFlags::empty()).unwrap();
+        txn.commit().unwrap();
+
+        let mut txn = env.begin_rw_txn().unwrap();
+        txn.nested(|child| child.put(db, b"key", b"child", PutFlags::empty()))
+            .unwrap();
+        assert_eq!(get(&txn, db, b"key"), Some(b"child".to_vec()));
+        txn.abort();
+
+        let txn = env.begin_ro_txn().unwrap();
+        assert_eq!(get(&txn, db, b"key"), None);
+    }
+
+    #[test]
+    fn test_nested_capture() {
+        let env = TestEnv::with(Environment::builder().capture_changes(true));
+        let events = env.change_feed().unwrap().channel();
+        let mut txn = env.begin_rw_txn().unwrap();
+        let db = txn.create_db(None, DbFlags::empty()).unwrap();
+        txn.put(db, b"parent", b"", PutFlags::empty()).unwrap();
+
+        let child = txn.begin_nested().unwrap();
+        child.put(db, b"aborted", b"", PutFlags::empty()).unwrap();
+        child.abort();
+        txn.nested(|child| {
+            child.put(db, b"failed", b"", PutFlags::empty())?;
+            Err::<(), _>(Error::from_code(MDBX_EINVAL))
+        })
+        .unwrap_err();
+        txn.nested(|child| child.put(db, b"committed", b"", PutFlags::empty()))
+            .unwrap();
+        // Nothing is delivered before the outermost transaction commits
+        assert!(events.try_recv().is_err());
+
+        let txnid = txn.id();
+        txn.commit().unwrap();
+        let event = events.try_recv().unwrap();
+        assert_eq!(event.txnid, txnid);
+        assert_eq!(event.tables, [db]);
+        assert_eq!(
+            event.keys,
+            [(db, b"committed".to_vec()), (db, b"parent".to_vec())]
+        );
+        assert!(events.try_recv().is_err());
+    }
+}
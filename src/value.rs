@@ -0,0 +1,106 @@
+//! Values returned by the safe API.
+
+use std::fmt;
+use std::ops::Deref;
+
+/// A value read from the database.
+///
+/// Values from clean pages borrow directly from the memory map for the
+/// lifetime of the transaction. Values from pages dirtied by the current
+/// write transaction may move on the next mutation, so they are copied.
+#[derive(Clone)]
+pub enum Value<'txn> {
+    /// Zero-copy slice into a clean page.
+    Borrowed(&'txn [u8]),
+    /// Copy of a value from a dirty page.
+    Owned(Vec<u8>),
+}
+
+impl<'txn> Value<'txn> {
+    /// Whether the value borrows directly from the memory map.
+    #[inline]
+    pub fn is_borrowed(&self) -> bool {
+        matches!(self, Value::Borrowed(_))
+    }
+
+    /// Convert into an owned byte vector, copying if borrowed.
+    pub fn into_owned(self) -> Vec<u8> {
+        match self {
+            Value::Borrowed(bytes) => bytes.to_vec(),
+            Value::Owned(bytes) => bytes,
+        }
+    }
+}
+
+impl Deref for Value<'_> {
+    type Target = [u8];
+
+    #[inline]
+    fn deref(&self) -> &[u8] {
+        match self {
+            Value::Borrowed(bytes) => bytes,
+            Value::Owned(bytes) => bytes,
+        }
+    }
+}
+
+impl AsRef<[u8]> for Value<'_> {
+    #[inline]
+    fn as_ref(&self) -> &[u8] {
+        self
+    }
+}
+
+impl PartialEq for Value<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        **self == **other
+    }
+}
+
+impl Eq for Value<'_> {}
+
+impl std::hash::Hash for Value<'_> {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        (**self).hash(state)
+    }
+}
+
+impl PartialEq<[u8]> for Value<'_> {
+    fn eq(&self, other: &[u8]) -> bool {
+        **self == *other
+    }
+}
+
+impl PartialEq<&[u8]> for Value<'_> {
+    fn eq(&self, other: &&[u8]) -> bool {
+        **self == **other
+    }
+}
+
+impl fmt::Debug for Value<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = if self.is_borrowed() {
+            "Borrowed"
+        } else {
+            "Owned"
+        };
+        f.debug_tuple(kind).field(&&**self).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_value_deref() {
+        let data = b"hello".to_vec();
+        let borrowed = Value::Borrowed(&data);
+        let owned = Value::Owned(data.clone());
+        assert!(borrowed.is_borrowed());
+        assert!(!owned.is_borrowed());
+        assert_eq!(borrowed, owned);
+        assert_eq!(&*borrowed, b"hello");
+        assert_eq!(owned.into_owned(), data);
+    }
+}
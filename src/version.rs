@@ -0,0 +1,134 @@
+//! Version and build metadata of the linked engine.
+
+use std::ffi::CStr;
+use std::os::raw::c_char;
+use std::sync::OnceLock;
+
+use crate::constants::MDBX_VERSION_MISMATCH;
+use crate::error::{Error, Result};
+use crate::{mdbx_build, mdbx_version};
+
+/// Version and build information of the linked engine.
+///
+/// The static library is fetched by `build.rs` and may be stale in `OUT_DIR`,
+/// so this is the only reliable way to tell which engine is actually linked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EngineVersion {
+    pub major: u16,
+    pub minor: u16,
+    pub patch: u16,
+    pub tweak: u16,
+    /// Output of `git describe` for the engine sources.
+    pub describe: &'static str,
+    /// Build timestamp.
+    pub datetime: &'static str,
+    /// Target the engine was built for.
+    pub target: &'static str,
+    /// Build options the engine was compiled with.
+    pub options: &'static str,
+}
+
+impl EngineVersion {
+    /// Whether the engine version matches the given `major.minor.patch` string.
+    pub fn matches(&self, version: &str) -> bool {
+        parse_version(version) == Some((self.major, self.minor, self.patch))
+    }
+}
+
+/// Version and build information of the linked engine.
+pub fn engine_version() -> EngineVersion {
+    // SAFETY: both statics are initialized by the engine and never modified.
+    unsafe {
+        EngineVersion {
+            major: mdbx_version.major,
+            minor: mdbx_version.minor,
+            patch: mdbx_version.patch,
+            tweak: mdbx_version.tweak,
+            describe: static_str(mdbx_version.git.describe),
+            datetime: static_str(mdbx_build.datetime),
+            target: static_str(mdbx_build.target),
+            options: static_str(mdbx_build.options),
+        }
+    }
+}
+
+/// Check once per process that the engine matches this crate's version.
+pub(crate) fn check_engine_version() -> Result<()> {
+    static CHECKED: OnceLock<bool> = OnceLock::new();
+    let matches = *CHECKED.get_or_init(|| engine_version().matches(env!("CARGO_PKG_VERSION")));
+    if matches {
+        Ok(())
+    } else {
+        Err(Error::from_code(MDBX_VERSION_MISMATCH))
+    }
+}
+
+fn parse_version(version: &str) -> Option<(u16, u16, u16)> {
+    // Ignore pre-release and build metadata suffixes
+    let core = version.split(['-', '+']).next()?;
+    let mut parts = core.split('.').map(|p| p.parse::<u16>().ok());
+    let version = (parts.next()??, parts.next()??, parts.next()??);
+    if parts.next().is_some() {
+        return None;
+    }
+    Some(version)
+}
+
+/// # Safety
+///
+/// `ptr` must be null or point to a NUL-terminated string with static lifetime.
+unsafe fn static_str(ptr: *const c_char) -> &'static str {
+    if ptr.is_null() {
+        ""
+    } else {
+        CStr::from_ptr(ptr).to_str().unwrap_or("")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn version(major: u16, minor: u16, patch: u16) -> EngineVersion {
+        EngineVersion {
+            major,
+            minor,
+            patch,
+            tweak: 0,
+            describe: "",
+            datetime: "",
+            target: "",
+            options: "",
+        }
+    }
+
+    #[test]
+    fn test_parse_version() {
+        assert_eq!(parse_version("0.4.3"), Some((0, 4, 3)));
+        assert_eq!(parse_version("1.2.3-rc.1"), Some((1, 2, 3)));
+        assert_eq!(parse_version("1.2.3+build"), Some((1, 2, 3)));
+        assert_eq!(parse_version("1.2"), None);
+        assert_eq!(parse_version("1.2.3.4"), None);
+        assert_eq!(parse_version("a.b.c"), None);
+    }
+
+    #[test]
+    fn test_version_matches() {
+        assert!(version(0, 4, 3).matches("0.4.3"));
+        assert!(!version(0, 4, 2).matches("0.4.3"));
+        assert!(!version(0, 13, 0).matches("0.4.3"));
+    }
+
+    #[test]
+    fn test_engine_build_info() {
+        // datetime, target, options, compiler, flags, metadata
+        assert_eq!(
+            std::mem::size_of::<crate::MDBX_build_info>(),
+            6 * std::mem::size_of::<*const c_char>()
+        );
+        let engine = engine_version();
+        assert!(!engine.target.is_empty());
+        assert_ne!(engine.target, engine.options);
+        assert!(check_engine_version().is_ok());
+    }
+}